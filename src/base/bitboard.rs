@@ -0,0 +1,332 @@
+use std::ops::{BitAnd, BitOr, Not, Sub};
+use std::fmt::{Display, Formatter, Error};
+
+use base::coord::*;
+
+/// Rows a bitboard can hold, i.e. the biggest board side.
+const ROWS : usize = 32;
+
+/// A set of points of a board up to 32x32: one u32 per row, bit n being column n. Neighbours,
+/// flood fills and liberties are a few shifts and masks per row, and nothing is ever allocated.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct BitBoard {
+    size :u8,
+    rows :[u32; ROWS]
+}
+
+impl BitBoard {
+
+    /// an empty set for a board of the given size.
+    pub fn new(size :u8) -> Self {
+        debug_assert!(size as usize <= ROWS && size > 0, "bitboards go from 1x1 to 32x32, not {}", size);
+        BitBoard {
+            size,
+            rows : [0; ROWS]
+        }
+    }
+
+    /// every point of a board of the given size.
+    pub fn full(size :u8) -> Self {
+        let mut bb = BitBoard::new(size);
+        let mask = bb.row_mask();
+        for row in bb.rows[..size as usize].iter_mut() {
+            *row = mask;
+        }
+        bb
+    }
+
+    pub fn from_coords(size :u8, coords :&[Coord]) -> Self {
+        let mut bb = BitBoard::new(size);
+        for c in coords {
+            bb.insert(c);
+        }
+        bb
+    }
+
+    #[inline]
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    #[inline]
+    pub fn contains(&self, coord :&Coord) -> bool {
+        coord.row < self.size && coord.col < self.size && self.rows[coord.row as usize] & (1 << coord.col) != 0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, coord :&Coord) {
+        debug_assert!(coord.row < self.size && coord.col < self.size, "{} is out of a {}x{} board", coord, self.size, self.size);
+        self.rows[coord.row as usize] |= 1 << coord.col;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, coord :&Coord) {
+        debug_assert!(coord.row < self.size && coord.col < self.size, "{} is out of a {}x{} board", coord, self.size, self.size);
+        self.rows[coord.row as usize] &= !(1 << coord.col);
+    }
+
+    /// the points of a row as bits, column 0 first; rows past the board are empty.
+    #[inline]
+    pub fn row(&self, row :usize) -> u32 {
+        self.rows[row % ROWS]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&r| r == 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.rows.iter().map(|r| r.count_ones() as usize).sum()
+    }
+
+    /// the first point, row by row.
+    pub fn first(&self) -> Option<Coord> {
+        self.iter().next()
+    }
+
+    /// the nth point (from zero), row by row; handy to pick a random one.
+    pub fn nth(&self, n :usize) -> Option<Coord> {
+        let mut left = n;
+        for (r, &row) in self.rows.iter().enumerate() {
+            let ones = row.count_ones() as usize;
+            if left < ones {
+                let mut bits = row;
+                for _ in 0..left {
+                    bits &= bits - 1;
+                }
+                return Some(Coord::new_us(r, bits.trailing_zeros() as usize))
+            }
+            left -= ones;
+        }
+        None
+    }
+
+    pub fn iter(&self) -> BitBoardIter {
+        BitBoardIter {
+            rows : self.rows,
+            row  : 0
+        }
+    }
+
+    /// the set plus the points next to it.
+    pub fn dilate(&self) -> BitBoard {
+        let n = self.size as usize;
+        let mask = self.row_mask();
+        let mut res = BitBoard::new(self.size);
+        for r in 0..n {
+            let row = self.rows[r];
+            let mut d = row | ((row << 1) & mask) | (row >> 1);
+            if r > 0 {
+                d |= self.rows[r - 1];
+            }
+            if r + 1 < n {
+                d |= self.rows[r + 1];
+            }
+            res.rows[r] = d;
+        }
+        res
+    }
+
+    /// the points next to the set and not in it; the liberties of a chain are its adjacents
+    /// which are empty.
+    pub fn adjacents(&self) -> BitBoard {
+        self.dilate() - *self
+    }
+
+    /// grows the set through the points in within, i.e. the connected parts of within touching
+    /// the set (what is out of within is dropped).
+    pub fn flood(&self, within :&BitBoard) -> BitBoard {
+        let mut curr = *self & *within;
+        loop {
+            let next = curr.dilate() & *within;
+            if next == curr {
+                return curr
+            }
+            curr = next;
+        }
+    }
+
+    #[inline]
+    fn row_mask(&self) -> u32 {
+        u32::MAX >> (ROWS - self.size as usize)
+    }
+}
+
+pub struct BitBoardIter {
+    rows :[u32; ROWS],
+    row  :usize
+}
+
+impl Iterator for BitBoardIter {
+    type Item = Coord;
+
+    fn next(&mut self) -> Option<Coord> {
+        while self.row < ROWS {
+            let bits = self.rows[self.row];
+            if bits != 0 {
+                self.rows[self.row] = bits & (bits - 1);
+                return Some(Coord::new_us(self.row, bits.trailing_zeros() as usize))
+            }
+            self.row += 1;
+        }
+        None
+    }
+}
+
+impl IntoIterator for &BitBoard {
+    type Item = Coord;
+    type IntoIter = BitBoardIter;
+
+    fn into_iter(self) -> BitBoardIter {
+        self.iter()
+    }
+}
+
+impl BitOr for BitBoard {
+    type Output = BitBoard;
+
+    fn bitor(self, other :BitBoard) -> BitBoard {
+        debug_assert_eq!(self.size, other.size);
+        let mut res = self;
+        for (r, o) in res.rows.iter_mut().zip(other.rows.iter()) {
+            *r |= *o;
+        }
+        res
+    }
+}
+
+impl BitAnd for BitBoard {
+    type Output = BitBoard;
+
+    fn bitand(self, other :BitBoard) -> BitBoard {
+        debug_assert_eq!(self.size, other.size);
+        let mut res = self;
+        for (r, o) in res.rows.iter_mut().zip(other.rows.iter()) {
+            *r &= *o;
+        }
+        res
+    }
+}
+
+impl Sub for BitBoard {
+    type Output = BitBoard;
+
+    fn sub(self, other :BitBoard) -> BitBoard {
+        debug_assert_eq!(self.size, other.size);
+        let mut res = self;
+        for (r, o) in res.rows.iter_mut().zip(other.rows.iter()) {
+            *r &= !*o;
+        }
+        res
+    }
+}
+
+impl Not for BitBoard {
+    type Output = BitBoard;
+
+    /// the points of the board not in the set.
+    fn not(self) -> BitBoard {
+        BitBoard::full(self.size) - self
+    }
+}
+
+impl Display for BitBoard {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for r in (0..self.size).rev() {
+            for c in 0..self.size {
+                f.write_str(if self.contains(&Coord::new(r, c)) { "X" } else { "." })?;
+            }
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn coords(cs :&[&str]) -> Vec<Coord> {
+        cs.iter().map(|c| Coord::from_str(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn it_inserts_and_removes() {
+        let mut bb = BitBoard::new(19);
+        assert!(bb.is_empty());
+        let c = Coord::from_str("D4").unwrap();
+        bb.insert(&c);
+        assert!(bb.contains(&c));
+        assert_eq!(1, bb.count());
+        assert_eq!(Some(c), bb.first());
+        bb.remove(&c);
+        assert!(!bb.contains(&c));
+        assert!(bb.is_empty());
+        assert!(!bb.contains(&Coord::new(19, 0)));
+    }
+
+    #[test]
+    fn it_fills_every_size() {
+        for size in 1..33 {
+            let full = BitBoard::full(size);
+            assert_eq!((size as usize).pow(2), full.count());
+            assert!((!full).is_empty());
+            assert_eq!(full, full.dilate());
+            assert_eq!(full, !BitBoard::new(size));
+        }
+    }
+
+    #[test]
+    fn it_finds_adjacents_at_the_edges() {
+        let corner = BitBoard::from_coords(9, &coords(&["A1"]));
+        assert_eq!(BitBoard::from_coords(9, &coords(&["A2", "B1"])), corner.adjacents());
+        let side = BitBoard::from_coords(9, &coords(&["J5"]));
+        assert_eq!(BitBoard::from_coords(9, &coords(&["J4", "J6", "H5"])), side.adjacents());
+        let top = BitBoard::from_coords(32, &[Coord::new(31, 31)]);
+        assert_eq!(BitBoard::from_coords(32, &[Coord::new(30, 31), Coord::new(31, 30)]), top.adjacents());
+        let two = BitBoard::from_coords(9, &coords(&["D4", "E4"]));
+        assert_eq!(6, two.adjacents().count());
+    }
+
+    #[test]
+    fn it_floods_within() {
+        // a wall on the C column splits the board
+        let wall = BitBoard::from_coords(9, &coords(&["C1", "C2", "C3", "C4", "C5", "C6", "C7", "C8", "C9"]));
+        let seed = BitBoard::from_coords(9, &coords(&["A5"]));
+        let flooded = seed.flood(&!wall);
+        assert_eq!(18, flooded.count());
+        assert!(flooded.contains(&Coord::from_str("B9").unwrap()));
+        assert!(!flooded.contains(&Coord::from_str("D5").unwrap()));
+        assert_eq!(wall, flooded.adjacents());
+        // seeds out of within are dropped
+        assert!(seed.flood(&wall).is_empty());
+    }
+
+    #[test]
+    fn it_iterates_and_picks_in_order() {
+        let cs = vec!(Coord::new(0, 3), Coord::new(0, 31), Coord::new(5, 0), Coord::new(31, 31));
+        let bb = BitBoard::from_coords(32, &cs);
+        assert_eq!(cs, bb.iter().collect::<Vec<Coord>>());
+        for (n, c) in cs.iter().enumerate() {
+            assert_eq!(Some(*c), bb.nth(n));
+        }
+        assert_eq!(None, bb.nth(4));
+    }
+
+    #[test]
+    fn it_combines_sets() {
+        let a = BitBoard::from_coords(9, &coords(&["A1", "B1"]));
+        let b = BitBoard::from_coords(9, &coords(&["B1", "C1"]));
+        assert_eq!(BitBoard::from_coords(9, &coords(&["A1", "B1", "C1"])), a | b);
+        assert_eq!(BitBoard::from_coords(9, &coords(&["B1"])), a & b);
+        assert_eq!(BitBoard::from_coords(9, &coords(&["A1"])), a - b);
+        assert_eq!(79, (!a).count());
+    }
+
+}
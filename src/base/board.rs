@@ -4,40 +4,104 @@ use std::hash::{Hash, Hasher};
 use base::color::*;
 use base::moves::*;
 use base::coord::*;
+use base::bitboard::*;
 
 include!("zobrist-const.rs");
 
+/// marks the points which are not part of a chain
+const NO_CHAIN : u16 = u16::MAX;
+
+/// the colors a point can have, the most common first.
+#[cfg(any(test, feature = "bitboards"))]
+const COLORS : [Color; 6] = [Color::Empty, Color::Black, Color::White, Color::Dame, Color::BlackTerritory, Color::WhiteTerritory];
+
+/// Besides the colors, the board keeps its chains up to date as stones come and go: every stone
+/// knows the head of its chain and the next stone in it (a circular list), and heads keep the
+/// chain length and its pseudo liberties (the empty points next to each stone, counted once per
+/// stone touching them), which are zero only when the chain has no liberties at all. The colors
+/// are a point array; with the `bitboards` feature they are a bitboard per color instead, which
+/// answers the whole board questions (regions, counts, a random empty point) without gathering
+/// the points first, at the cost of slower single point reads (see PERFORMANCE.md).
 #[derive(Clone, Eq, Debug)]
 pub struct Board  {
     size :u8,
+    #[cfg(not(feature = "bitboards"))]
     data :Vec<Color>,
-    zobrist :u64
+    zobrist :u64,
+    links :Vec<Link>,
+    #[cfg(feature = "bitboards")]
+    bits :[BitBoard; 7],
+    /// 2^16 / size rounded up, rows are (offset * it) >> 16 without dividing
+    #[cfg(feature = "bitboards")]
+    row_factor :u32
+}
+
+/// An empty point surrounded by stones of a color.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Eye {
+    /// the opponent has enough diagonals to fill it once the stones around are in atari
+    False,
+    /// only the owner can fill it
+    Real
+}
+
+/// where a point stands in its chain, length and liberties are only kept up to date in heads.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Link {
+    head :u16,
+    next :u16,
+    len  :u16,
+    libs :u16
+}
+
+#[inline]
+fn is_stone(color :Color) -> bool {
+    color == Color::Black || color == Color::White
 }
 
 impl Board {
     pub fn new(board_size :usize) -> Self {
         debug_assert!(board_size<=BOARD_MAX_SIDE, format!("I'm sorry boards up to {} are possible; is 19 not enough?", BOARD_MAX_SIDE));
         debug_assert!(board_size>4, "Do you really want a board smaller than 5x5?");
+        let points = board_size*board_size;
         Board {
             size : board_size as u8,
-            data : (0..board_size*board_size).map(|_| Color::Empty).collect(),
-            zobrist : LE_ZOBRIST_BOARD[board_size-1]
+            #[cfg(not(feature = "bitboards"))]
+            data : (0..points).map(|_| Color::Empty).collect(),
+            zobrist : LE_ZOBRIST_BOARD[board_size-1],
+            links : (0..points as u16).map(|o| Link { head : NO_CHAIN, next : o, len : 0, libs : 0 }).collect(),
+            #[cfg(feature = "bitboards")]
+            bits : {
+                let mut bits = [BitBoard::new(board_size as u8); 7];
+                bits[Color::Empty as usize] = BitBoard::full(board_size as u8);
+                bits
+            },
+            #[cfg(feature = "bitboards")]
+            row_factor : (1u32 << 16).div_ceil(board_size as u32)
         }
     }
 
     pub fn set_move(&mut self, m :Move) {
         if let Move::Stone(coord, color) = m {
             let o = self.data_offset(&coord);
-            //zobrist
-            let curr = self.data[o];
-            if curr == Color::Black || curr == Color::White {
-                self.zobrist = self.zobrist ^ LE_ZOBRISTS[curr as usize][o];
+            let curr = self.color_at(o);
+            if curr == color {
+                return
             }
-            if color == Color::Black || color == Color::White {
-                self.zobrist = self.zobrist ^ LE_ZOBRISTS[color as usize][o];
+            // first the point is emptied ...
+            if is_stone(curr) {
+                self.remove_stone(o);
+            } else if curr != Color::Empty {
+                self.put(o, Color::Empty);
+                self.adjacent_chains_gain_liberty(o);
+            }
+            // ... then filled
+            if is_stone(color) {
+                self.add_stone(o, color);
+            } else if color != Color::Empty {
+                self.adjacent_chains_lose_liberty(o);
+                self.put(o, color);
             }
-            // position
-            self.data[o] = color;
         }
     }
 
@@ -47,89 +111,508 @@ impl Board {
         }
     }
 
+    #[cfg(feature = "bitboards")]
+    #[inline]
+    pub fn get(&self, coord :&Coord) -> Color {
+        self.color_in_row(coord.row as usize, coord.col as usize)
+    }
+
+    #[cfg(not(feature = "bitboards"))]
+    #[inline]
     pub fn get(&self, coord :&Coord) -> Color {
         self.data[self.data_offset(coord)]
     }
 
     pub fn find_first(&self, color :Color) -> Option<Coord> {
-        match self.data.iter().enumerate().find(|e| *e.1 == color) {
-            Some(e) => Some( self.offset_to_coord(e.0) ),
-            None    => None
-        }
+        self.points(color).first()
     }
 
     pub fn find_next(&self, color :Color, coord :&Coord) -> Option<Coord> {
         let delta = self.data_offset(coord)+1;
-        match self.data.iter().skip(delta).enumerate().find(|e| *e.1 == color) {
-            Some(e) => Some( self.offset_to_coord(delta + e.0) ),
-            None    => None
-        }
+        (delta..self.links.len()).find(|&o| self.color_at(o) == color).map(|o| self.offset_to_coord(o))
     }
 
     pub fn adjacents_by_color(&self, center :&Coord, color :&Color) -> Vec<Coord> {
         center.adjacents(self.size).into_iter().filter(|c|self.get(&c) == *color).collect()
     }
 
-    // TODO: TEST
+    /// true if the point is empty and its neighbours are stones of the given color, it says
+    /// nothing about it being a real eye.
     pub fn is_eye(&self, eye :&Coord, color :&Color) -> bool {
-        self.get(eye) == Color::Empty && eye.adjacents(self.size).into_iter().all(|c| self.get(&c) == *color)
-    }
-
-    // TODO: TEST
-    fn find_at_least_one_liberty_internal(&self, paint :&mut Vec<Coord>, our_color :Color, coord : Coord) -> bool {
-        // assumes current coord is already in the set and our color
-        for adj in coord.adjacents(self.size) {
-            if !paint.contains(&adj) {
-                let adj_col = self.get(&adj);
-                if adj_col == Color::Empty {
-                    return true
-                } else if adj_col == our_color {
-                    paint.push(adj);
-                    if self.find_at_least_one_liberty_internal(paint, our_color, adj) {
-                        return true;
-                    }
-                }
+        let o = self.data_offset(eye);
+        let (ns, n) = self.neighbours(o);
+        self.color_at(o) == Color::Empty && ns[..n].iter().all(|&a| self.color_at(a) == *color)
+    }
+
+    /// tells real from false eyes by their diagonals: an opponent stone on one of them is enough
+    /// to make an eye false at the edge or the corner, it takes two in the middle of the board.
+    pub fn eye(&self, eye :&Coord, color :&Color) -> Option<Eye> {
+        if !self.is_eye(eye, color) {
+            return None
+        }
+        let size = self.size as isize;
+        let opposite = color.opposite();
+        let mut off_board = 0;
+        let mut opponent = 0;
+        for &(dr, dc) in &[(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let (row, col) = (eye.row as isize + dr, eye.col as isize + dc);
+            if row < 0 || col < 0 || row >= size || col >= size {
+                off_board += 1;
+            } else if self.color_at((row * size + col) as usize) == opposite {
+                opponent += 1;
             }
         }
-        false
+        if (off_board > 0 && opponent > 0) || opponent > 1 {
+            Some(Eye::False)
+        } else {
+            Some(Eye::Real)
+        }
+    }
+
+    /// the empty and opponent points connected to the given one and surrounded by stones of the
+    /// given color, if there are no more than max_points of them; a (maybe big) eye to be.
+    pub fn eye_space(&self, point :&Coord, color :&Color, max_points :usize) -> Option<BitBoard> {
+        if self.get(point) == *color || !is_stone(*color) {
+            return None
+        }
+        let within = self.points(Color::Empty) | self.points(color.opposite());
+        let space = BitBoard::from_coords(self.size, &[*point]).flood(&within);
+        let bounded = (space.adjacents() - self.points(*color)).is_empty();
+        if bounded && !space.is_empty() && space.count() <= max_points {
+            Some(space)
+        } else {
+            None
+        }
     }
 
-    // TODO: TEST
+    /// true if the move's stone would have a liberty once placed, not counting captures; it is
+    /// also fine to ask once the stone is on the board.
     pub fn given_move_will_live(&self, m :Move) -> bool {
         match m {
             Move::Pass(_)  => false,
             Move::Stone(coord, color) => {
-                let mut paint : Vec<Coord> = Vec::with_capacity(self.size as usize);
-                paint.push (coord);
-                self.find_at_least_one_liberty_internal(&mut paint, color, coord)
+                let o = self.data_offset(&coord);
+                if self.color_at(o) == color {
+                    return self.links[self.links[o].head as usize].libs > 0
+                }
+                let (ns, n) = self.neighbours(o);
+                ns[..n].iter().any(|&a| self.color_at(a) == Color::Empty
+                    || (self.color_at(a) == color && self.has_liberty_other_than(a, o)))
             }
         }
     }
 
-    // TODO: TEST
+    /// true if `given` is the only liberty left to the `adj_color` chain at `adj`.
     pub fn is_given_coord_last_liberty_for_adj_chain(&self, given :Coord, adj: Coord, adj_color :Color) -> bool {
-        let mut paint : Vec<Coord> = Vec::with_capacity(self.size as usize);
-        paint.push(given);
-        !self.find_at_least_one_liberty_internal(&mut paint, adj_color, adj)
+        let a = self.data_offset(&adj);
+        self.color_at(a) == adj_color && !self.has_liberty_other_than(a, self.data_offset(&given))
+    }
+
+    /// true if the chain at coord has a liberty, in constant time; false for no stone.
+    #[inline]
+    pub fn has_liberties(&self, coord :&Coord) -> bool {
+        let o = self.data_offset(coord);
+        is_stone(self.color_at(o)) && self.links[self.links[o].head as usize].libs > 0
+    }
+
+    /// the liberties of the chain at coord, in O(chain); nothing if there is no stone.
+    pub fn liberties(&self, coord :&Coord) -> Vec<Coord> {
+        let mut libs = vec!();
+        self.visit_liberties(self.data_offset(coord), |l| libs.push(l));
+        libs.into_iter().map(|l| self.offset_to_coord(l)).collect()
+    }
+
+    /// how many liberties the chain at coord has, in O(chain) and without allocating.
+    pub fn liberty_count(&self, coord :&Coord) -> usize {
+        let mut count = 0;
+        self.visit_liberties(self.data_offset(coord), |_| count += 1);
+        count
+    }
+
+    /// how many stones the chain at coord has, in constant time.
+    pub fn chain_len(&self, coord :&Coord) -> usize {
+        let o = self.data_offset(coord);
+        if is_stone(self.color_at(o)) { self.links[self.links[o].head as usize].len as usize } else { 0 }
     }
 
     pub fn remove_chain(&mut self, coord : Coord, color : Color) -> usize {
-        if self.get(&coord) != color {
-            return 0;
-        }
-        let mut count = 1;
-        self.set_move( Move::Stone(coord, Color::Empty) );
-        for adj in coord.adjacents(self.size) {
-            if self.get(&adj) == color {
-                count = count + self.remove_chain(adj, color);
+        let mut removed = vec!();
+        self.take_chain(coord, color, &mut removed);
+        removed.len()
+    }
+
+    /// removes the chain, appending its stones into removed (handy for undoing)
+    pub fn take_chain(&mut self, coord : Coord, color : Color, removed :&mut Vec<Coord>) {
+        let o = self.data_offset(&coord);
+        if !is_stone(color) || self.color_at(o) != color {
+            return;
+        }
+        let first = removed.len();
+        let mut s = o;
+        loop {
+            removed.push(self.offset_to_coord(s));
+            self.put(s, Color::Empty);
+            self.links[s].head = NO_CHAIN;
+            self.zobrist ^= LE_ZOBRISTS[color as usize][s];
+            s = self.links[s].next as usize;
+            if s == o {
+                break;
             }
         }
-        count
+        // once the whole chain is gone, its neighbours get the liberties
+        for c in &removed[first..] {
+            let s = self.data_offset(c);
+            self.adjacent_chains_gain_liberty(s);
+        }
+    }
+
+    /// removes the chains of the given color next to center which have no liberties left, i.e.
+    /// the ones captured by a stone just placed at center.
+    pub fn take_chains_without_liberties(&mut self, center :&Coord, color :Color, removed :&mut Vec<Coord>) {
+        let (ns, n) = self.neighbours(self.data_offset(center));
+        for &a in &ns[..n] {
+            if self.color_at(a) == color && self.links[self.links[a].head as usize].libs == 0 {
+                let coord = self.offset_to_coord(a);
+                self.take_chain(coord, color, removed);
+            }
+        }
+    }
+
+    /// the points of the given color, e.g. the empty ones to flood regions or pick a random point.
+    #[cfg(feature = "bitboards")]
+    #[inline]
+    pub fn points(&self, color :Color) -> BitBoard {
+        self.bits[color as usize]
+    }
+
+    /// the points of the given color, e.g. the empty ones to flood regions or pick a random point.
+    #[cfg(not(feature = "bitboards"))]
+    pub fn points(&self, color :Color) -> BitBoard {
+        let mut points = BitBoard::new(self.size);
+        for (o, _) in self.data.iter().enumerate().filter(|e| *e.1 == color) {
+            points.insert(&self.offset_to_coord(o));
+        }
+        points
+    }
+
+    // chains bookkeeping
+
+    #[cfg(feature = "bitboards")]
+    #[inline]
+    fn put(&mut self, o :usize, color :Color) {
+        let coord = self.offset_to_coord(o);
+        let curr = self.get(&coord);
+        self.bits[curr as usize].remove(&coord);
+        self.bits[color as usize].insert(&coord);
+    }
+
+    #[cfg(not(feature = "bitboards"))]
+    #[inline]
+    fn put(&mut self, o :usize, color :Color) {
+        self.data[o] = color;
+    }
+
+    #[cfg(feature = "bitboards")]
+    #[inline]
+    fn color_at(&self, o :usize) -> Color {
+        let row = (o as u32 * self.row_factor) as usize >> 16;
+        self.color_in_row(row, o - row * self.size as usize)
+    }
+
+    #[cfg(not(feature = "bitboards"))]
+    #[inline]
+    fn color_at(&self, o :usize) -> Color {
+        self.data[o]
     }
 
-    /// The following is handy for fast observation
-    pub fn data(&self) -> &Vec<Color> {
-        &(self.data)
+    /// the one bitboard having the point tells its color, stones and empty points first.
+    #[cfg(feature = "bitboards")]
+    #[inline]
+    fn color_in_row(&self, row :usize, col :usize) -> Color {
+        let bit = 1 << col;
+        if self.bits[Color::Empty as usize].row(row) & bit != 0 {
+            Color::Empty
+        } else if self.bits[Color::Black as usize].row(row) & bit != 0 {
+            Color::Black
+        } else if self.bits[Color::White as usize].row(row) & bit != 0 {
+            Color::White
+        } else {
+            *COLORS[3..].iter().find(|&&color| self.bits[color as usize].row(row) & bit != 0)
+                .expect("every point has a color")
+        }
+    }
+
+    #[inline]
+    fn neighbours(&self, o :usize) -> ([usize; 4], usize) {
+        let size = self.size as usize;
+        let col = o % size;
+        let mut ns = [0; 4];
+        let mut n = 0;
+        if o + size < size * size {
+            ns[n] = o + size;
+            n += 1;
+        }
+        if o >= size {
+            ns[n] = o - size;
+            n += 1;
+        }
+        if col + 1 < size {
+            ns[n] = o + 1;
+            n += 1;
+        }
+        if col > 0 {
+            ns[n] = o - 1;
+            n += 1;
+        }
+        (ns, n)
+    }
+
+    fn adjacent_chains_gain_liberty(&mut self, o :usize) {
+        let (ns, n) = self.neighbours(o);
+        for &a in &ns[..n] {
+            let h = self.links[a].head;
+            if is_stone(self.color_at(a)) && h != NO_CHAIN {
+                self.links[h as usize].libs += 1;
+            }
+        }
+    }
+
+    fn adjacent_chains_lose_liberty(&mut self, o :usize) {
+        let (ns, n) = self.neighbours(o);
+        for &a in &ns[..n] {
+            if is_stone(self.color_at(a)) {
+                let h = self.links[a].head as usize;
+                self.links[h].libs -= 1;
+            }
+        }
+    }
+
+    fn add_stone(&mut self, o :usize, color :Color) {
+        self.adjacent_chains_lose_liberty(o);
+        self.put(o, color);
+        self.zobrist ^= LE_ZOBRISTS[color as usize][o];
+        let (ns, n) = self.neighbours(o);
+        self.links[o].head = o as u16;
+        self.links[o].next = o as u16;
+        self.links[o].len = 1;
+        self.links[o].libs = ns[..n].iter().filter(|&&a| self.color_at(a) == Color::Empty).count() as u16;
+        for &a in &ns[..n] {
+            if self.color_at(a) == color && self.links[a].head != self.links[o].head {
+                let (ha, ho) = (self.links[a].head, self.links[o].head);
+                self.merge_chains(ha, ho);
+            }
+        }
+    }
+
+    fn merge_chains(&mut self, a :u16, b :u16) {
+        let (big, small) = if self.links[a as usize].len >= self.links[b as usize].len { (a as usize, b as usize) } else { (b as usize, a as usize) };
+        let mut s = small;
+        loop {
+            self.links[s].head = big as u16;
+            s = self.links[s].next as usize;
+            if s == small {
+                break;
+            }
+        }
+        // splices both circular lists
+        let big_next = self.links[big].next;
+        self.links[big].next = self.links[small].next;
+        self.links[small].next = big_next;
+        self.links[big].len += self.links[small].len;
+        self.links[big].libs += self.links[small].libs;
+    }
+
+    /// takes a single stone away, what is left of its chain may be split so it is rebuilt; every
+    /// piece touches the removed stone, so it is enough to rebuild from its neighbours.
+    fn remove_stone(&mut self, o :usize) {
+        let color = self.color_at(o);
+        let mut s = o;
+        loop {
+            self.links[s].head = NO_CHAIN;
+            s = self.links[s].next as usize;
+            if s == o {
+                break;
+            }
+        }
+        self.put(o, Color::Empty);
+        self.zobrist ^= LE_ZOBRISTS[color as usize][o];
+        self.adjacent_chains_gain_liberty(o);
+        let (ns, n) = self.neighbours(o);
+        for &a in &ns[..n] {
+            if self.color_at(a) == color && self.links[a].head == NO_CHAIN {
+                self.rebuild_chain(a);
+            }
+        }
+    }
+
+    /// links the stones of the given color not in a chain and connected to start, the list
+    /// itself is used as the queue.
+    fn rebuild_chain(&mut self, start :usize) {
+        let color = self.color_at(start);
+        self.links[start].head = start as u16;
+        let mut len = 1;
+        let mut libs = 0;
+        let mut tail = start;
+        let mut curr = start;
+        loop {
+            let (ns, n) = self.neighbours(curr);
+            for &a in &ns[..n] {
+                if self.color_at(a) == Color::Empty {
+                    libs += 1;
+                } else if self.color_at(a) == color && self.links[a].head == NO_CHAIN {
+                    self.links[a].head = start as u16;
+                    self.links[tail].next = a as u16;
+                    tail = a;
+                    len += 1;
+                }
+            }
+            if curr == tail {
+                break;
+            }
+            curr = self.links[curr].next as usize;
+        }
+        self.links[tail].next = start as u16;
+        self.links[start].len = len;
+        self.links[start].libs = libs;
+    }
+
+    fn has_liberty_other_than(&self, o :usize, other :usize) -> bool {
+        if !is_stone(self.color_at(o)) {
+            return false
+        }
+        let mut s = o;
+        loop {
+            let (ns, n) = self.neighbours(s);
+            if ns[..n].iter().any(|&a| a != other && self.color_at(a) == Color::Empty) {
+                return true
+            }
+            s = self.links[s].next as usize;
+            if s == o {
+                return false
+            }
+        }
+    }
+
+    /// the first liberty found walking the chain at o, none if it has none or there is no stone.
+    fn first_liberty(&self, o :usize) -> Option<usize> {
+        if !is_stone(self.color_at(o)) {
+            return None
+        }
+        let mut s = o;
+        loop {
+            let (ns, n) = self.neighbours(s);
+            if let Some(&a) = ns[..n].iter().find(|&&a| self.color_at(a) == Color::Empty) {
+                return Some(a)
+            }
+            s = self.links[s].next as usize;
+            if s == o {
+                return None
+            }
+        }
+    }
+
+    fn visit_liberties<F>(&self, o :usize, mut visit :F) where F: FnMut(usize) {
+        if !is_stone(self.color_at(o)) {
+            return
+        }
+        let mut seen = [0u64; BOARD_MAX_SIDE * BOARD_MAX_SIDE / 64];
+        let mut s = o;
+        loop {
+            let (ns, n) = self.neighbours(s);
+            for &a in &ns[..n] {
+                if self.color_at(a) == Color::Empty && seen[a / 64] & (1 << (a % 64)) == 0 {
+                    seen[a / 64] |= 1 << (a % 64);
+                    visit(a);
+                }
+            }
+            s = self.links[s].next as usize;
+            if s == o {
+                return
+            }
+        }
+    }
+
+    /// checks the chains bookkeeping against chains found from scratch.
+    #[cfg(test)]
+    pub fn assert_chains_consistent(&self) {
+        let mut all = BitBoard::new(self.size);
+        for &color in &COLORS {
+            let points = self.points(color);
+            assert!((all & points).is_empty(), "{} points have another color too", color);
+            all = all | points;
+            let listed : Vec<Coord> = (0..self.links.len()).filter(|&o| self.color_at(o) == color).map(|o| self.offset_to_coord(o)).collect();
+            assert_eq!(listed, points.iter().collect::<Vec<Coord>>(), "{} points", color);
+        }
+        assert_eq!(BitBoard::full(self.size), all);
+        for o in 0..self.links.len() {
+            if !is_stone(self.color_at(o)) {
+                continue
+            }
+            let coord = self.offset_to_coord(o);
+            let mut chain = vec!(coord);
+            let mut i = 0;
+            while i < chain.len() {
+                for adj in chain[i].adjacents(self.size) {
+                    if self.get(&adj) == self.color_at(o) && !chain.contains(&adj) {
+                        chain.push(adj);
+                    }
+                }
+                i += 1;
+            }
+            let mut listed = self.chain_coords(coord);
+            assert_eq!(chain.len(), listed.len(), "chain at {}", coord);
+            listed.retain(|c| chain.contains(c));
+            assert_eq!(chain.len(), listed.len(), "chain at {}", coord);
+            assert_eq!(chain.len(), self.chain_len(&coord), "chain length at {}", coord);
+            let pseudo : usize = chain.iter().map(|c| self.adjacents_by_color(c, &Color::Empty).len()).sum();
+            assert_eq!(pseudo, self.links[self.links[o].head as usize].libs as usize, "pseudo liberties at {}", coord);
+        }
+        let stones = self.points(Color::Black).count() + self.points(Color::White).count();
+        assert_eq!(stones, self.chains().map(|c| c.stone_count()).sum::<usize>(), "stones in chains");
+    }
+
+    /// the chain at coord, if there is a stone.
+    pub fn chain_at(&self, coord :&Coord) -> Option<Chain<'_>> {
+        let o = self.data_offset(coord);
+        if is_stone(self.color_at(o)) {
+            Some(Chain { board : self, head : self.links[o].head as usize })
+        } else {
+            None
+        }
+    }
+
+    /// every chain on the board, both colors.
+    pub fn chains(&self) -> Chains<'_> {
+        Chains { board : self, next : 0 }
+    }
+
+    /// the chains of the given color with a single liberty left.
+    pub fn chains_in_atari(&self, color :Color) -> impl Iterator<Item = Chain<'_>> {
+        self.chains().filter(move |c| c.color() == color && c.in_atari())
+    }
+
+    /// the coords of the chain the given coord is part of, nothing if it is empty.
+    pub fn chain_coords(&self, coord :Coord) -> Vec<Coord> {
+        let o = self.data_offset(&coord);
+        if !is_stone(self.color_at(o)) {
+            return vec!()
+        }
+        let mut chain = Vec::with_capacity(self.links[self.links[o].head as usize].len as usize);
+        let mut s = o;
+        loop {
+            chain.push(self.offset_to_coord(s));
+            s = self.links[s].next as usize;
+            if s == o {
+                return chain
+            }
+        }
+    }
+
+    /// what a stone of the given color adds to (or takes from) the zobrist hash at coord.
+    #[inline]
+    pub fn zobrist_key(&self, coord :&Coord, color :Color) -> u64 {
+        debug_assert!(color == Color::Black || color == Color::White, "Only stones are hashed, not '{}'", color);
+        LE_ZOBRISTS[color as usize][self.data_offset(coord)]
     }
 
     #[inline]
@@ -155,6 +638,140 @@ impl Board {
     }
 }
 
+/// xored into a position hash when white is to move, so situations can be told apart. The
+/// zobrist table for empty points is never used by the board, so its keys are free.
+#[inline]
+pub fn zobrist_white_to_move() -> u64 {
+    LE_ZOBRISTS[Color::Empty as usize][0]
+}
+
+/// xored into a situation hash when the last move was a pass, another pass would end the game.
+#[inline]
+pub fn zobrist_after_pass() -> u64 {
+    LE_ZOBRISTS[Color::Empty as usize][1]
+}
+
+
+/// A chain as it is on a board, it goes stale once the board changes.
+#[derive(Copy, Clone, Debug)]
+pub struct Chain<'a> {
+    board :&'a Board,
+    head  :usize
+}
+
+impl<'a> Chain<'a> {
+
+    pub fn color(&self) -> Color {
+        self.board.color_at(self.head)
+    }
+
+    /// the stone keeping the chain bookkeeping, any two stones of a chain give the same one.
+    pub fn head(&self) -> Coord {
+        self.board.offset_to_coord(self.head)
+    }
+
+    pub fn stone_count(&self) -> usize {
+        self.board.links[self.head].len as usize
+    }
+
+    pub fn contains(&self, coord :&Coord) -> bool {
+        let o = self.board.data_offset(coord);
+        is_stone(self.board.color_at(o)) && self.board.links[o].head as usize == self.head
+    }
+
+    pub fn stones(&self) -> ChainStones<'a> {
+        ChainStones { board : self.board, head : self.head, next : Some(self.head) }
+    }
+
+    pub fn points(&self) -> BitBoard {
+        let mut points = BitBoard::new(self.board.size);
+        for c in self.stones() {
+            points.insert(&c);
+        }
+        points
+    }
+
+    pub fn liberties(&self) -> BitBoard {
+        self.points().adjacents() & self.board.points(Color::Empty)
+    }
+
+    pub fn liberty_count(&self) -> usize {
+        let mut count = 0;
+        self.board.visit_liberties(self.head, |_| count += 1);
+        count
+    }
+
+    /// a single liberty left, stops looking as soon as it finds a second one.
+    pub fn in_atari(&self) -> bool {
+        match self.board.first_liberty(self.head) {
+            Some(l) => !self.board.has_liberty_other_than(self.head, l),
+            None    => false
+        }
+    }
+
+    /// the opponent chains touching this one, each once.
+    pub fn adjacent_chains(&self) -> Vec<Chain<'a>> {
+        let opposite = self.color().opposite();
+        let mut adjacents : Vec<Chain<'a>> = vec!();
+        for c in self.stones() {
+            let (ns, n) = self.board.neighbours(self.board.data_offset(&c));
+            for &a in &ns[..n] {
+                if self.board.color_at(a) == opposite {
+                    let head = self.board.links[a].head as usize;
+                    if !adjacents.iter().any(|ch| ch.head == head) {
+                        adjacents.push(Chain { board : self.board, head });
+                    }
+                }
+            }
+        }
+        adjacents
+    }
+}
+
+impl<'a> PartialEq for Chain<'a> {
+    fn eq(&self, other :&Chain) -> bool {
+        self.head == other.head && ::std::ptr::eq(self.board, other.board)
+    }
+}
+
+/// the stones of a chain, starting by its head.
+pub struct ChainStones<'a> {
+    board :&'a Board,
+    head  :usize,
+    next  :Option<usize>
+}
+
+impl<'a> Iterator for ChainStones<'a> {
+    type Item = Coord;
+
+    fn next(&mut self) -> Option<Coord> {
+        let curr = self.next?;
+        let next = self.board.links[curr].next as usize;
+        self.next = if next == self.head { None } else { Some(next) };
+        Some(self.board.offset_to_coord(curr))
+    }
+}
+
+/// the chains of a board, by the offset of their heads.
+pub struct Chains<'a> {
+    board :&'a Board,
+    next  :usize
+}
+
+impl<'a> Iterator for Chains<'a> {
+    type Item = Chain<'a>;
+
+    fn next(&mut self) -> Option<Chain<'a>> {
+        while self.next < self.board.links.len() {
+            let o = self.next;
+            self.next += 1;
+            if is_stone(self.board.color_at(o)) && self.board.links[o].head as usize == o {
+                return Some(Chain { board : self.board, head : o })
+            }
+        }
+        None
+    }
+}
 
 impl Display for Board {
     fn fmt(&self, fmt : &mut Formatter) -> Result<(), Error> {
@@ -238,6 +855,222 @@ mod tests {
         assert_eq!(board.get(&Coord::new(3,2)), Color::Black);
     }
 
+    #[test]
+    fn it_finds_chain_coords() {
+        let mut board = given_board_with_two_moves();
+        board.set_move(Move::Stone(Coord::new(3,3), Color::Black));
+        board.set_move(Move::Stone(Coord::new(4,3), Color::Black));
+        let chain = board.chain_coords(Coord::new(4,3));
+        assert_eq!(3, chain.len());
+        assert!(chain.contains(&Coord::new(3,2)));
+        assert!(chain.contains(&Coord::new(3,3)));
+        assert_eq!(vec!(Coord::new(2,3)), board.chain_coords(Coord::new(2,3)));
+        assert!(board.chain_coords(Coord::new(0,0)).is_empty());
+    }
+
+    #[test]
+    fn it_tracks_liberties_while_chains_merge() {
+        let mut board = Board::new(9);
+        board.set_moves(vec!(Move::from_str("black a1").unwrap(), Move::from_str("black c1").unwrap()));
+        assert_eq!(2, board.liberty_count(&Coord::from_str("A1").unwrap()));
+        assert_eq!(3, board.liberty_count(&Coord::from_str("C1").unwrap()));
+        board.set_move(Move::from_str("black b1").unwrap());
+        let a1 = Coord::from_str("A1").unwrap();
+        assert_eq!(3, board.chain_len(&a1));
+        assert_eq!(4, board.liberty_count(&a1));
+        let mut libs = board.liberties(&a1);
+        libs.sort_by_key(|c| (c.row, c.col));
+        assert_eq!(vec!(Coord::from_str("D1").unwrap(), Coord::from_str("A2").unwrap(),
+                        Coord::from_str("B2").unwrap(), Coord::from_str("C2").unwrap()), libs);
+        board.set_moves(vec!(Move::from_str("white a2").unwrap(), Move::from_str("white b2").unwrap(),
+                             Move::from_str("white c2").unwrap()));
+        assert_eq!(1, board.liberty_count(&a1));
+        assert!(board.has_liberties(&a1));
+        board.set_move(Move::from_str("white d1").unwrap());
+        assert!(!board.has_liberties(&a1));
+        assert_eq!(0, board.liberty_count(&a1));
+        assert!(!board.has_liberties(&Coord::from_str("E5").unwrap()));
+        board.assert_chains_consistent();
+    }
+
+    #[test]
+    fn it_splits_chains_when_a_stone_is_removed() {
+        let mut board = Board::new(9);
+        for m in &["black c3", "black d3", "black e3", "black d4", "black d2"] {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        assert_eq!(5, board.chain_len(&Coord::from_str("C3").unwrap()));
+        board.set_move(Move::Stone(Coord::from_str("D3").unwrap(), Color::Empty));
+        for c in &["C3", "E3", "D4", "D2"] {
+            assert_eq!(1, board.chain_len(&Coord::from_str(c).unwrap()));
+        }
+        assert_eq!(4, board.liberty_count(&Coord::from_str("D4").unwrap()));
+        board.assert_chains_consistent();
+        // a stone changing color
+        board.set_move(Move::from_str("white d3").unwrap());
+        assert_eq!(3, board.liberty_count(&Coord::from_str("D4").unwrap()));
+        assert_eq!(0, board.liberty_count(&Coord::from_str("D3").unwrap()));
+        board.assert_chains_consistent();
+    }
+
+    #[test]
+    fn it_takes_chains_giving_liberties_back() {
+        let mut board = Board::new(9);
+        for m in &["black a1", "black b1", "white a2", "white b2", "white c1"] {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        let mut removed = vec!();
+        board.take_chain(Coord::from_str("B1").unwrap(), Color::Black, &mut removed);
+        assert_eq!(2, removed.len());
+        assert_eq!(Color::Empty, board.get(&Coord::from_str("A1").unwrap()));
+        assert_eq!(5, board.liberty_count(&Coord::from_str("A2").unwrap()));
+        assert_eq!(3, board.liberty_count(&Coord::from_str("C1").unwrap()));
+        let mut expected = Board::new(9);
+        expected.set_moves(vec!(Move::from_str("white a2").unwrap(), Move::from_str("white b2").unwrap(),
+                                Move::from_str("white c1").unwrap()));
+        assert_eq!(expected.zobrist(), board.zobrist());
+        board.assert_chains_consistent();
+    }
+
+    #[test]
+    fn it_views_chains() {
+        let mut board = Board::new(9);
+        for m in &["black c3", "black d3", "white c4", "white d4", "white e3", "white c2"] {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        let c3 = Coord::from_str("C3").unwrap();
+        let chain = board.chain_at(&c3).unwrap();
+        assert_eq!(Color::Black, chain.color());
+        assert_eq!(2, chain.stone_count());
+        assert!(chain.contains(&Coord::from_str("D3").unwrap()));
+        assert!(!chain.contains(&Coord::from_str("C4").unwrap()));
+        assert_eq!(chain, board.chain_at(&Coord::from_str("D3").unwrap()).unwrap());
+        assert_eq!(chain.head(), board.chain_at(&Coord::from_str("D3").unwrap()).unwrap().head());
+        let mut stones : Vec<Coord> = chain.stones().collect();
+        stones.sort_by_key(|c| (c.row, c.col));
+        assert_eq!(board.chain_coords(c3).len(), stones.len());
+        assert_eq!(vec!(c3, Coord::from_str("D3").unwrap()), stones);
+        assert_eq!(BitBoard::from_coords(9, &[Coord::from_str("B3").unwrap(), Coord::from_str("D2").unwrap()]), chain.liberties());
+        assert_eq!(2, chain.liberty_count());
+        assert!(!chain.in_atari());
+        assert_eq!(3, chain.adjacent_chains().len());
+        assert!(board.chain_at(&Coord::from_str("E5").unwrap()).is_none());
+    }
+
+    #[test]
+    fn it_lists_chains_and_chains_in_atari() {
+        let mut board = Board::new(9);
+        for m in &["black c3", "black d3", "white c4", "white d4", "white e3", "white c2", "white b3"] {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        assert_eq!(5, board.chains().count());
+        assert_eq!(7, board.chains().map(|c| c.stone_count()).sum::<usize>());
+        assert_eq!(1, board.chains().filter(|c| c.color() == Color::Black).count());
+        let atari : Vec<Chain> = board.chains_in_atari(Color::Black).collect();
+        assert_eq!(1, atari.len());
+        assert_eq!(BitBoard::from_coords(9, &[Coord::from_str("D2").unwrap()]), atari[0].liberties());
+        assert_eq!(4, atari[0].adjacent_chains().len());
+        assert_eq!(0, board.chains_in_atari(Color::White).count());
+        board.set_move(Move::from_str("black b2").unwrap());
+        board.set_move(Move::from_str("black c1").unwrap());
+        assert_eq!(vec!(Coord::from_str("C2").unwrap()), board.chains_in_atari(Color::White).map(|c| c.head()).collect::<Vec<Coord>>());
+    }
+
+    fn board_with(size :usize, moves :&[&str]) -> Board {
+        let mut board = Board::new(size);
+        for m in moves {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        board
+    }
+
+    fn eye_at(board :&Board, coord :&str, color :Color) -> Option<Eye> {
+        board.eye(&Coord::from_str(coord).unwrap(), &color)
+    }
+
+    #[test]
+    fn it_finds_eye_shapes() {
+        let board = board_with(9, &["black d4", "black f4", "black e3", "black e5", "white e4"]);
+        assert!(!board.is_eye(&Coord::from_str("E4").unwrap(), &Color::Black));
+        let board = board_with(9, &["black d4", "black f4", "black e3", "black e5"]);
+        assert!(board.is_eye(&Coord::from_str("E4").unwrap(), &Color::Black));
+        assert!(!board.is_eye(&Coord::from_str("E4").unwrap(), &Color::White));
+        assert!(!board.is_eye(&Coord::from_str("D5").unwrap(), &Color::Black));
+    }
+
+    #[test]
+    fn it_tells_real_from_false_eyes_in_the_middle() {
+        let mut board = board_with(9, &["black d4", "black f4", "black e3", "black e5"]);
+        assert_eq!(Some(Eye::Real), eye_at(&board, "E4", Color::Black));
+        board.set_move(Move::from_str("white d5").unwrap());
+        assert_eq!(Some(Eye::Real), eye_at(&board, "E4", Color::Black));
+        board.set_move(Move::from_str("white f3").unwrap());
+        assert_eq!(Some(Eye::False), eye_at(&board, "E4", Color::Black));
+        assert_eq!(None, eye_at(&board, "E4", Color::White));
+    }
+
+    #[test]
+    fn it_tells_real_from_false_eyes_at_the_edge_and_corner() {
+        let mut board = board_with(9, &["black d1", "black f1", "black e2"]);
+        assert_eq!(Some(Eye::Real), eye_at(&board, "E1", Color::Black));
+        board.set_move(Move::from_str("white d2").unwrap());
+        assert_eq!(Some(Eye::False), eye_at(&board, "E1", Color::Black));
+
+        let mut board = board_with(9, &["black a2", "black b1"]);
+        assert_eq!(Some(Eye::Real), eye_at(&board, "A1", Color::Black));
+        board.set_move(Move::from_str("white b2").unwrap());
+        assert_eq!(Some(Eye::False), eye_at(&board, "A1", Color::Black));
+    }
+
+    #[test]
+    fn it_finds_eye_spaces() {
+        // a three points eye in the corner, with a white stone inside
+        let board = board_with(9, &["black a2", "black b2", "black c2", "black d1", "white b1"]);
+        let a1 = Coord::from_str("A1").unwrap();
+        let space = board.eye_space(&a1, &Color::Black, 3).unwrap();
+        assert_eq!(BitBoard::from_coords(9, &[a1, Coord::from_str("B1").unwrap(), Coord::from_str("C1").unwrap()]), space);
+        assert_eq!(Some(space), board.eye_space(&Coord::from_str("B1").unwrap(), &Color::Black, 7));
+        assert_eq!(None, board.eye_space(&a1, &Color::Black, 2));
+        // the rest of the board is not an eye
+        assert_eq!(None, board.eye_space(&Coord::from_str("E5").unwrap(), &Color::Black, 7));
+        assert_eq!(None, board.eye_space(&Coord::from_str("A2").unwrap(), &Color::Black, 7));
+        assert_eq!(None, board.eye_space(&a1, &Color::White, 7));
+    }
+
+    #[test]
+    fn it_keeps_chains_with_territory_marks() {
+        let mut board = given_board_with_two_moves();
+        let black = Coord::new(3,2);
+        assert_eq!(4, board.liberty_count(&black));
+        board.set_move(Move::Stone(Coord::new(4,2), Color::BlackTerritory));
+        assert_eq!(3, board.liberty_count(&black));
+        board.set_move(Move::Stone(Coord::new(4,2), Color::Empty));
+        assert_eq!(4, board.liberty_count(&black));
+        board.assert_chains_consistent();
+    }
+
+    #[test]
+    fn it_keeps_chains_consistent_under_random_changes() {
+        let colors = [Color::Empty, Color::Black, Color::White, Color::Dame];
+        let mut board = Board::new(9);
+        for i in 0..2000 {
+            let m = Move::Stone(Coord::random(9), colors[i * 7 % 4]);
+            board.set_move(m);
+            if i % 50 == 0 {
+                board.assert_chains_consistent();
+            }
+        }
+        board.assert_chains_consistent();
+    }
+
+    #[test]
+    fn it_hashes_with_zobrist_keys() {
+        let board = given_board_with_two_moves();
+        let empty = Board::new(19);
+        assert_eq!(empty.zobrist() ^ board.zobrist_key(&Coord::new(2,3), Color::White) ^ board.zobrist_key(&Coord::new(3,2), Color::Black),
+                   board.zobrist());
+    }
+
     #[test]
     fn it_handles_board_limits() {
         let mut board = Board::new(BOARD_MAX_SIDE);
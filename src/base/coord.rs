@@ -86,6 +86,18 @@ impl Coord {
         Ok(Self::new(board_size-bz-1, az))
     }
 
+    /// inverse of from_sgf, i.e. A19 is 'aa' and T1 is 'ss' in a 19x19 board.
+    pub fn to_sgf(&self, board_size :u8) -> String {
+        let mut s = String::with_capacity(2);
+        s.push(Self::sgf_char(self.col));
+        s.push(Self::sgf_char(board_size - self.row - 1));
+        s
+    }
+
+    fn sgf_char(n :u8) -> char {
+        if n < 26 { (n + b'a') as char } else { (n - 26 + b'A') as char }
+    }
+
 }
 
 impl FromStr for Coord {
@@ -324,6 +336,27 @@ mod tests {
         assert_eq!(Coord::from_str("J1").unwrap(),  Coord::from_sgf("ii", 9).unwrap());
     }
 
+    #[test]
+    fn it_does_to_sgf() {
+        assert_eq!("aa", Coord::from_str("A19").unwrap().to_sgf(19));
+        assert_eq!("sa", Coord::from_str("T19").unwrap().to_sgf(19));
+        assert_eq!("ss", Coord::from_str("T1").unwrap().to_sgf(19));
+        assert_eq!("as", Coord::from_str("A1").unwrap().to_sgf(19));
+        assert_eq!("ee", Coord::from_str("E5").unwrap().to_sgf(9));
+        assert_eq!("ia", Coord::from_str("J9").unwrap().to_sgf(9));
+        assert_eq!("AA", Coord::new(0, 26).to_sgf(27));
+    }
+
+    #[test]
+    fn it_roundtrips_to_sgf_from_sgf() {
+        for coord in Coord::all_possibles(19) {
+            assert_eq!(coord, Coord::from_sgf(&coord.to_sgf(19), 19).unwrap());
+        }
+        for coord in Coord::all_possibles(9) {
+            assert_eq!(coord, Coord::from_sgf(&coord.to_sgf(9), 9).unwrap());
+        }
+    }
+
     #[test]
     fn it_handles_invalid_from_sgf() {
         assert!(Coord::from_sgf("sa", 5).is_err());
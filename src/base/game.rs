@@ -1,7 +1,7 @@
 use std::fmt::{Display, Formatter, Error};
-use std::str::FromStr;
 
 use std::collections::HashSet;
+use std::cmp;
 
 
 use base::color::*;
@@ -9,18 +9,36 @@ use base::moves::*;
 use base::coord::*;
 use base::board::*;
 use base::gametree::*;
+use base::rules::*;
 
 #[derive(Clone, Debug)]
 pub struct Game {
     board    : Board,
     komi     : f32,
     handicap : u16,
+    rules    : Rules,
     captured_black : u16,
     captured_white : u16,
     next_turn : Color,
     finished : bool,
     super_ko : HashSet<u64>,
-    moves : Vec<Move>
+    ko       : Option<Coord>,
+    moves : Vec<Move>,
+    undo_stack : Vec<Undo>
+}
+
+/// what it takes to undo a move, captured stones are of the opposite color of the move and
+/// suicided ones of its own color.
+#[derive(Clone, Debug)]
+struct Undo {
+    captured : Vec<Coord>,
+    suicided : Vec<Coord>,
+    captured_black : u16,
+    captured_white : u16,
+    next_turn : Color,
+    finished : bool,
+    ko : Option<Coord>,
+    super_ko_inserted : bool
 }
 
 impl Game {
@@ -34,24 +52,157 @@ impl Game {
             board : le_board,
             komi : komi,
             handicap : handicap as u16,
+            rules : Rules::default(),
             captured_black : 0,
             captured_white : 0,
             next_turn : if handicap>0 {Color::White} else {Color::Black},
             finished : false,
             super_ko : HashSet::new(),
-            moves : Vec::with_capacity((board_size*board_size)) //FIXME: do better estimation
+            ko : None,
+            moves : Vec::with_capacity(board_size*board_size), //FIXME: do better estimation
             // japanese rules usually are 3/2 i.e. 250 moves in 19x19, but with chinese rules
             // it tends to be around 300.
+            undo_stack : Vec::with_capacity(board_size*board_size)
         }
     }
 
+    /// The game as in the root node of the GameTree, handicap stones are taken from the setup
+    /// stones (AB) when available, so free handicap and problems are reproduced as they are. A move
+    /// in the root is played too, so replaying the game goes on with gt.moves().
     pub fn new_for_gametree(gt :&GameTree) -> Self {
-        Self::new(gt.board_size(), gt.komi(), gt.handicap() as usize)
+        let root = gt.root();
+        let mut game;
+        if root.setup().is_empty() {
+            game = Self::new(gt.board_size(), gt.komi(), gt.handicap() as usize);
+        } else {
+            game = Self::new(gt.board_size(), gt.komi(), 0);
+            game.handicap = gt.handicap();
+            if game.handicap > 0 {
+                game.next_turn = Color::White;
+            }
+        }
+        if let Some(rules) = gt.rules() {
+            game.rules = rules;
+        }
+        // a move can come in the root node too
+        game.play_node(root);
+        game
+    }
+
+    /// A game starting from an arbitrary position, all chains in the board must have liberties.
+    pub fn new_for_position(board :Board, komi :f32, next_turn :Color) -> Result<Self, SetupError> {
+        let mut game = Self::new(board.size() as usize, komi, 0);
+        let stones : Vec<Move> = Coord::all_possibles(board.size() as usize).into_iter()
+            .map(|c| Move::Stone(c, board.get(&c)))
+            .filter(|m| m.color() != Color::Empty)
+            .collect();
+        game.setup_stones(&stones)?;
+        game.set_next_turn(next_turn);
+        Ok(game)
+    }
+
+    /// Places setup stones, or removes them when the color is Empty. Nothing is captured, so if a
+    /// chain ends up without liberties it fails and the game is left untouched. Moves before a
+    /// setup can't be undone.
+    pub fn setup_stones(&mut self, stones :&[Move]) -> Result<(), SetupError> {
+        let size = self.board.size();
+        let mut board = self.board.clone();
+        for m in stones {
+            match *m {
+                Move::Stone(coord, color) if color == Color::Black || color == Color::White || color == Color::Empty => {
+                    if coord.row >= size || coord.col >= size {
+                        return Err(SetupError::OutOfBoard(coord))
+                    }
+                    board.set_move(*m);
+                },
+                _ => return Err(SetupError::NotAStone(*m))
+            }
+        }
+        // only the placed stones and their neighbours could have lost liberties
+        for m in stones.iter().filter(|m| m.color() != Color::Empty) {
+            let coord = m.coord();
+            let mut chains = vec!(coord);
+            chains.extend(coord.adjacents(size));
+            for c in chains {
+                let color = board.get(&c);
+                if (color == Color::Black || color == Color::White) && !board.given_move_will_live(Move::Stone(c, color)) {
+                    return Err(SetupError::NoLiberties(c))
+                }
+            }
+        }
+        self.board = board;
+        let board = self.board.clone();
+        self.seed_super_ko(&board);
+        self.ko = None;
+        self.undo_stack.clear();
+        Ok(())
+    }
+
+    pub fn set_next_turn(&mut self, color :Color) {
+        debug_assert!(color == Color::Black || color == Color::White, "It should never be the turn for '{}'", color);
+        self.next_turn = color;
+    }
+
+    /// Adds a previous position to the superko history, i.e. when loading a position which was
+    /// reached by moves not known by this game. As it is not known who was to move, it counts for
+    /// both.
+    pub fn seed_super_ko(&mut self, board :&Board) {
+        self.super_ko.insert(board.zobrist());
+        self.super_ko.insert(board.zobrist() ^ zobrist_white_to_move());
+    }
+
+    /// Applies the setup of a node (SGF's AB, AW, AE and PL), setup stones are not moves so nothing
+    /// is captured, but the resulting position counts for superko. SGF files are taken as they
+    /// are, use `setup_stones` for a validated setup.
+    pub fn apply_setup(&mut self, gn :&GameNode) {
+        for m in gn.setup() {
+            self.board.set_move(*m);
+        }
+        if let Some(player) = gn.player() {
+            self.next_turn = player;
+        }
+        if !gn.setup().is_empty() {
+            let board = self.board.clone();
+            self.seed_super_ko(&board);
+            self.ko = None;
+            self.undo_stack.clear();
+        }
+    }
+
+    /// Replays a node of a GameTree: its setup and then its move, if any. As in SGF the move
+    /// says who plays, so the turn follows it.
+    pub fn play_node(&mut self, gn :&GameNode) -> bool {
+        self.apply_setup(gn);
+        let m = match gn.themove() {
+            Some(m) => m,
+            None    => return true
+        };
+        if !self.finished && self.next_turn != m.color() {
+            // the ko belonged to the other player
+            self.next_turn = m.color();
+            self.ko = None;
+        }
+        self.play(m)
     }
 
     pub fn board(&self) -> &Board { &self.board }
+
+    /// the hash of the stones on the board, the same as the board's zobrist.
+    pub fn position_hash(&self) -> u64 { self.board.zobrist() }
+
+    /// the hash of the position, who is to move and whether the last move was a pass; two games
+    /// with the same situation hash have the same future, handy for transposition tables.
+    pub fn situation_hash(&self) -> u64 {
+        let mut hash = self.board.zobrist() ^ Self::turn_key(self.next_turn);
+        if let Some(&Move::Pass(_)) = self.moves.last() {
+            hash ^= zobrist_after_pass();
+        }
+        hash
+    }
     pub fn komi(&self) -> f32 { self.komi }
     pub fn handicap(&self) -> u16 { self.handicap }
+    pub fn rules(&self) -> &Rules { &self.rules }
+    pub fn set_rules(&mut self, rules :Rules) { self.rules = rules }
     pub fn next_turn(&self) -> Color { self.next_turn }
     pub fn moves(&self) -> &Vec<Move> { &self.moves }
     pub fn move_count(&self) -> usize { self.moves.len() }
@@ -72,70 +223,187 @@ impl Game {
     // Mostly game logic below
 
     pub fn play(&mut self, m :Move) -> bool {
+        self.try_play(m).is_ok()
+    }
+
+    /// plays the move telling what happened, or why it could not be played.
+    pub fn try_play(&mut self, m :Move) -> Result<MoveOutcome, IllegalMove> {
 
         if self.finished {
-            return false
+            return Err(IllegalMove::GameFinished)
         }
 
-        if let Move::Pass(c) = m {
-            if c != self.next_turn {
-                return false
-            }
-            self.state_update_for_move(&m, 0);
-            return true;
+        if m.color() != self.next_turn {
+            return Err(IllegalMove::WrongColor(m.color()))
         }
 
-        if let Move::Stone(coord, color) = m {
+        let coord = match m {
+            Move::Pass(_) => {
+                self.state_update_for_move(&m, vec!(), vec!());
+                return Ok(MoveOutcome { captured : vec!(), suicided : vec!(), ko : None })
+            },
+            Move::Stone(coord, _) => coord
+        };
 
-            if color != self.next_turn {
-                return false
+        if coord.row >= self.board.size() || coord.col >= self.board.size() {
+            return Err(IllegalMove::OutOfBoard(coord))
+        }
+
+        if self.board.get(&coord) != Color::Empty {
+            return Err(IllegalMove::Occupied(coord))
+        }
+
+        if self.rules.ko == KoRule::Simple && self.ko == Some(coord) {
+            return Err(IllegalMove::Ko(coord))
+        }
+
+        let opposite_color = self.next_turn.opposite();
+        self.board.set_move(m);
+
+        // the board keeps the liberties, so captures are the neighbour chains left without any
+        let mut captured : Vec<Coord> = vec!();
+        self.board.take_chains_without_liberties(&coord, opposite_color, &mut captured);
+
+        // could place stone? (if not, nothing was captured)
+        let mut suicided : Vec<Coord> = vec!();
+        if !self.board.has_liberties(&coord) {
+            if !self.rules.suicide {
+                self.board.set_move(Move::Stone(coord, Color::Empty));
+                return Err(IllegalMove::Suicide(coord))
             }
+            self.board.take_chain(coord, m.color(), &mut suicided);
+        }
 
-            if self.board.get(&coord) != Color::Empty {
-                return false
+        if self.rules.ko != KoRule::Simple && self.repeats(self.board.zobrist(), opposite_color) {
+            // SUPERKO! the board goes back to how it was
+            for c in &suicided {
+                self.board.set_move(Move::Stone(*c, m.color()));
+            }
+            self.board.set_move(Move::Stone(coord, Color::Empty));
+            for c in &captured {
+                self.board.set_move(Move::Stone(*c, opposite_color));
             }
+            return Err(IllegalMove::SuperKo(coord))
+        }
 
-            let mut mock_board = self.board.clone();
+        // a lone stone with a single liberty that just took a single stone: that liberty is a ko
+        let ko = if captured.len() == 1
+            && self.board.adjacents_by_color(&coord, &m.color()).is_empty()
+            && self.board.adjacents_by_color(&coord, &Color::Empty).len() == 1 {
+            Some(captured[0])
+        } else {
+            None
+        };
 
-            let opposite_color = self.next_turn.opposite();
-            let mut captured = 0;
+        let outcome = MoveOutcome { captured : captured.clone(), suicided : suicided.clone(), ko };
+        self.state_update_for_move(&m, captured, suicided);
+        self.ko = ko;
+        Ok(outcome)
+    }
 
-            //println!(" all adj for {} are {:?} ", &coord, &coord.adjacents(self.board.size()) );
-            for adj in mock_board.adjacents_by_color(&coord, &opposite_color) {
-                //println!("is_given_coord_last_liberty_for_adj_chain {}, {}, {} = {}", coord, adj, opposite_color, mock_board.is_given_coord_last_liberty_for_adj_chain(coord, adj, opposite_color));
-                if mock_board.is_given_coord_last_liberty_for_adj_chain(coord, adj, opposite_color) {
-                    captured = captured + mock_board.remove_chain(adj, opposite_color);
-                }
+    /// the superko history keeps situations, so positional superko has to look for both turns.
+    fn repeats(&self, position :u64, turn :Color) -> bool {
+        match self.rules.ko {
+            KoRule::SituationalSuperko => self.super_ko.contains(&(position ^ Self::turn_key(turn))),
+            _ => self.super_ko.contains(&position) || self.super_ko.contains(&(position ^ zobrist_white_to_move()))
+        }
+    }
+
+    fn turn_key(turn :Color) -> u64 {
+        if turn == Color::White { zobrist_white_to_move() } else { 0 }
+    }
+
+    /// Tells if the move can be played, as `try_play` would, without touching the game.
+    pub fn is_legal(&self, m :Move) -> bool {
+        if self.finished || m.color() != self.next_turn {
+            return false
+        }
+        let coord = match m {
+            Move::Pass(_) => return true,
+            Move::Stone(coord, _) => coord
+        };
+        let size = self.board.size();
+        if coord.row >= size || coord.col >= size || self.board.get(&coord) != Color::Empty {
+            return false
+        }
+        if self.rules.ko == KoRule::Simple && self.ko == Some(coord) {
+            return false
+        }
+
+        let color = m.color();
+        let opposite_color = color.opposite();
+        let mut position = self.board.zobrist() ^ self.board.zobrist_key(&coord, color);
+        let mut captured : Vec<Coord> = vec!();
+        for adj in self.board.adjacents_by_color(&coord, &opposite_color) {
+            if !captured.contains(&adj) && self.board.is_given_coord_last_liberty_for_adj_chain(coord, adj, opposite_color) {
+                captured.extend(self.board.chain_coords(adj));
             }
+        }
+        for c in &captured {
+            position ^= self.board.zobrist_key(c, opposite_color);
+        }
 
-            // could place stone?
-            if mock_board.adjacents_by_color(&coord, &Color::Empty).len()>0 || mock_board.given_move_will_live(m) {
-                //println!("im playing apparently... {} with captured {}", &m, captured);
-                //println!("superkos are {:?} and myself is {}", self.super_ko, &mock_board.zobrist());
-                mock_board.set_move(m);
-                if self.super_ko.contains(&mock_board.zobrist()) {
-                    // SUPERKO!
-                    // println!("superko!");
-                    return false;
+        let lives = !captured.is_empty()
+            || !self.board.adjacents_by_color(&coord, &Color::Empty).is_empty()
+            || self.board.given_move_will_live(m);
+        if !lives {
+            if !self.rules.suicide {
+                return false
+            }
+            // the played stone goes, and the chains it joined too
+            position ^= self.board.zobrist_key(&coord, color);
+            let mut suicided : Vec<Coord> = vec!();
+            for adj in self.board.adjacents_by_color(&coord, &color) {
+                if !suicided.contains(&adj) {
+                    suicided.extend(self.board.chain_coords(adj));
                 }
-                self.board = mock_board;
-                self.state_update_for_move(&m, captured);
-
-                return true;
             }
+            for c in &suicided {
+                position ^= self.board.zobrist_key(c, color);
+            }
+        }
+
+        self.rules.ko == KoRule::Simple || !self.repeats(position, opposite_color)
+    }
+
+    /// Every legal move for the side to move, pass included (the last one); none once finished.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.finished {
+            return vec!()
         }
-        false
+        let mut moves : Vec<Move> = Coord::all_possibles(self.board.size() as usize).into_iter()
+            .map(|c| Move::Stone(c, self.next_turn))
+            .filter(|m| self.is_legal(*m))
+            .collect();
+        moves.push(Move::Pass(self.next_turn));
+        moves
     }
 
-    fn state_update_for_move(&mut self, m :&Move, captured :usize) {
+    fn state_update_for_move(&mut self, m :&Move, captured :Vec<Coord>, suicided :Vec<Coord>) {
         // it is a given the move is valid
+        let mut undo = Undo {
+            captured : Vec::with_capacity(0),
+            suicided : Vec::with_capacity(0),
+            captured_black : self.captured_black,
+            captured_white : self.captured_white,
+            next_turn : self.next_turn,
+            finished : self.finished,
+            ko : self.ko,
+            super_ko_inserted : false
+        };
+        self.ko = None;
         self.next_turn = self.next_turn.opposite();
         // captured
         match self.next_turn { // white kills, so 'blacks' are captured
-            Color::White => self.captured_white = self.captured_white + captured as u16,
-            Color::Black => self.captured_black = self.captured_black + captured as u16,
+            Color::White => self.captured_white += captured.len() as u16,
+            Color::Black => self.captured_black += captured.len() as u16,
             _ => panic!("It should never be the turn for '{}'", self.next_turn)
         }
+        // a suicide is captured by the opponent
+        match self.next_turn {
+            Color::White => self.captured_black += suicided.len() as u16,
+            _ => self.captured_white += suicided.len() as u16
+        }
         // two passes in a row, game is finished
         if let Move::Pass(_) = *m {
             if self.moves.len() > 0 {
@@ -146,7 +414,136 @@ impl Game {
         }
         // record the move
         self.moves.push(*m);
-        self.super_ko.insert(self.board.zobrist());
+        undo.super_ko_inserted = self.super_ko.insert(self.board.zobrist() ^ Self::turn_key(self.next_turn));
+        undo.captured = captured;
+        undo.suicided = suicided;
+        self.undo_stack.push(undo);
+    }
+
+    /// Takes back the last move, restoring captured stones, turn, captures count and superko
+    /// history; it is way cheaper than cloning the game before playing.
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.undo_stack.pop()?;
+        let m = self.moves.pop().unwrap();
+        if undo.super_ko_inserted {
+            self.super_ko.remove(&(self.board.zobrist() ^ Self::turn_key(self.next_turn)));
+        }
+        if let Move::Stone(coord, color) = m {
+            for c in &undo.suicided {
+                self.board.set_move(Move::Stone(*c, color));
+            }
+            self.board.set_move(Move::Stone(coord, Color::Empty));
+            for c in &undo.captured {
+                self.board.set_move(Move::Stone(*c, color.opposite()));
+            }
+        }
+        self.captured_black = undo.captured_black;
+        self.captured_white = undo.captured_white;
+        self.next_turn = undo.next_turn;
+        self.finished = undo.finished;
+        self.ko = undo.ko;
+        Some(m)
+    }
+
+    /// The most stones fixed handicap allows for the board size: none outside 7x7 to 25x25, only
+    /// the corners on even boards and 7x7, up to nine otherwise.
+    pub fn max_fixed_handicap(board_size :u8) -> usize {
+        if !(7..=25).contains(&board_size) {
+            0
+        } else if board_size & 1 == 0 || board_size == 7 {
+            4
+        } else {
+            9
+        }
+    }
+
+    /// The handicap points in the order of GTP's fixed_handicap: corners first, then the sides
+    /// (for 6 and 8 stones) and the centre for odd counts from 5. They are in the fourth line from
+    /// 13x13 and in the third one in smaller boards. None if it is more than the board allows.
+    pub fn fixed_handicap_coords(board_size :u8, handicap :usize) -> Option<Vec<Coord>> {
+        if handicap > Self::max_fixed_handicap(board_size) {
+            return None
+        }
+        let edge = if board_size >= 13 { 3 } else { 2 };
+        let far = board_size - 1 - edge;
+        let middle = board_size / 2;
+        let mut coords = vec!(Coord::new(edge, edge), Coord::new(far, far),
+                              Coord::new(far, edge), Coord::new(edge, far));
+        if handicap >= 6 {
+            coords.push(Coord::new(middle, edge));
+            coords.push(Coord::new(middle, far));
+        }
+        if handicap >= 8 {
+            coords.push(Coord::new(edge, middle));
+            coords.push(Coord::new(far, middle));
+        }
+        if handicap >= 5 && handicap % 2 == 1 {
+            coords.push(Coord::new(middle, middle));
+        }
+        coords.truncate(handicap);
+        Some(coords)
+    }
+
+    /// The fixed handicap points and, past as many as the board has, the most spread points
+    /// (the first of them when there are ties). At most all the points but one.
+    pub fn handicap_coords(board_size :u8, handicap :usize) -> Vec<Coord> {
+        let fixed = cmp::min(handicap, Self::max_fixed_handicap(board_size));
+        let mut coords = Self::fixed_handicap_coords(board_size, fixed).unwrap_or_default();
+        let max = cmp::min(handicap, (board_size as usize).pow(2) - 1);
+        while coords.len() < max {
+            let next = Self::most_spread_points(board_size, &coords)[0];
+            coords.push(next);
+        }
+        coords
+    }
+
+    /// The free points as far as they can be from the placed ones, or from the edges when none
+    /// is, and not closer to the edges than hoshi if possible: where to spread handicap stones.
+    pub fn most_spread_points(board_size :u8, placed :&[Coord]) -> Vec<Coord> {
+        // as hoshi: fourth line on big boards, third on small ones
+        let line = if board_size >= 13 { 3 } else if board_size >= 7 { 2 } else { 1 };
+        let free : Vec<Coord> = Coord::all_possibles(board_size as usize).into_iter()
+            .filter(|c| !placed.contains(c))
+            .collect();
+        let in_band : Vec<Coord> = free.iter().cloned().filter(|c| Self::edge_distance(c, board_size) >= line).collect();
+        let candidates = if in_band.is_empty() { free } else { in_band };
+
+        let spread = |c :&Coord| placed.iter().map(|p| Self::distance(c, p)).min()
+            .unwrap_or_else(|| Self::edge_distance(c, board_size));
+        let best_spread = candidates.iter().map(&spread).max().unwrap_or(0);
+        candidates.into_iter().filter(|c| spread(c) == best_spread).collect()
+    }
+
+    /// lines between the point and the closest edge.
+    pub fn edge_distance(c :&Coord, board_size :u8) -> u8 {
+        cmp::min(cmp::min(c.row, c.col), cmp::min(board_size - 1 - c.row, board_size - 1 - c.col))
+    }
+
+    /// how many king moves apart two points are.
+    pub fn distance(a :&Coord, b :&Coord) -> u8 {
+        cmp::max((a.row as i16 - b.row as i16).abs(), (a.col as i16 - b.col as i16).abs()) as u8
+    }
+
+    /// Places handicap stones wherever asked, as GTP's set_free_handicap; at least two and it has
+    /// to be done before anything is on the board. White moves next.
+    pub fn set_free_handicap(&mut self, coords :&[Coord]) -> Result<(), SetupError> {
+        if !self.moves.is_empty() || self.board.points(Color::Empty).count() != self.board.size() as usize * self.board.size() as usize {
+            return Err(SetupError::BoardNotEmpty)
+        }
+        let size = self.board.size() as usize;
+        if coords.len() < 2 || coords.len() >= size * size {
+            return Err(SetupError::InvalidHandicap(coords.len()))
+        }
+        for (i, coord) in coords.iter().enumerate() {
+            if coords[..i].contains(coord) {
+                return Err(SetupError::Duplicated(*coord))
+            }
+        }
+        let stones : Vec<Move> = coords.iter().map(|c| Move::Stone(*c, Color::Black)).collect();
+        self.setup_stones(&stones)?;
+        self.handicap = coords.len() as u16;
+        self.next_turn = Color::White;
+        Ok(())
     }
 
     pub fn pretty_print(&self) -> String {
@@ -221,34 +618,53 @@ impl Game {
 
     // mostly private
 
-    fn handicap_coords_for(board_size :u8) -> Vec<Coord> {
-        // no fast, but this doesn't need to be too fast.
-        match board_size {
-            19 => vec!( Coord::from_str(&"D4").unwrap(),  Coord::from_str(&"Q16").unwrap(),
-                        Coord::from_str(&"D16").unwrap(), Coord::from_str(&"Q4").unwrap(),
-                        Coord::from_str(&"D10").unwrap(), Coord::from_str(&"Q10").unwrap(),
-                        Coord::from_str(&"K4").unwrap(),  Coord::from_str(&"K16").unwrap(),
-                        Coord::from_str(&"K10").unwrap() ),
-            _ => vec!()
-        }
-    }
-
     fn markers_coords(board_size :u8) -> Vec<Coord> {
         // no fast, but this doesn't need to be fast, used only by pretty_print
-        // in the future, when more handicap is added, this will need to be limit to the first 9 in 19x19, etc.
-        Self::handicap_coords_for(board_size)
+        Self::fixed_handicap_coords(board_size, Self::max_fixed_handicap(board_size)).unwrap_or_default()
     }
 
     fn set_handicap_stones(board : &mut Board, handicap :usize) {
-        let coords = Self::handicap_coords_for(board.size());
-        assert!(coords.len()>0, format!("I dont know how to process handicaps for boards of size {}", board.size()));
-        for i in 0..handicap {
-            board.set_move(Move::Stone(coords[i], Color::Black))
+        for coord in Self::handicap_coords(board.size(), handicap) {
+            board.set_move(Move::Stone(coord, Color::Black))
         }
     }
 
 }
 
+/// what a legal move did to the board.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MoveOutcome {
+    /// stones taken off the board, of the opposite color of the move.
+    pub captured : Vec<Coord>,
+    /// own stones taken off the board, the played one included, when the rules allow suicide.
+    pub suicided : Vec<Coord>,
+    /// the point the opponent cannot retake right away, if the move started a ko.
+    pub ko : Option<Coord>
+}
+
+/// why a move was refused.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum IllegalMove {
+    GameFinished,
+    WrongColor(Color),
+    OutOfBoard(Coord),
+    Occupied(Coord),
+    Suicide(Coord),
+    /// retaking a ko right away, when the rules only forbid that
+    Ko(Coord),
+    SuperKo(Coord)
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SetupError {
+    NotAStone(Move),
+    OutOfBoard(Coord),
+    NoLiberties(Coord),
+    BoardNotEmpty,
+    InvalidHandicap(usize),
+    Duplicated(Coord)
+}
+
 impl Display for Game {
     fn fmt(&self, fmt : &mut Formatter) -> Result<(), Error> {
         fmt.write_fmt(format_args!("({}x{}#{} k={:2} h={} cb={} cw={} #{:x})",
@@ -274,6 +690,7 @@ mod tests {
     use base::color::*;
     use base::coord::*;
     use base::moves::*;
+    use base::sgf;
 
     fn assert_color(g :&Game, c :Color, pos :&str) {
         assert_eq!(c, g.board().get(&Coord::from_str(pos).unwrap()));
@@ -322,6 +739,321 @@ mod tests {
         assert_color(&g, Color::Empty, "Q4");
     }
 
+    fn coords(coords :Vec<&str>) -> Vec<Coord> {
+        coords.into_iter().map(|c| Coord::from_str(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn handicap_follows_gtp_fixed_handicap_order() {
+        assert_eq!(Some(coords(vec!("D4", "Q16", "D16", "Q4", "K10"))), Game::fixed_handicap_coords(19, 5));
+        assert_eq!(Some(coords(vec!("D4", "Q16", "D16", "Q4", "D10", "Q10"))), Game::fixed_handicap_coords(19, 6));
+        assert_eq!(Some(coords(vec!("D4", "Q16", "D16", "Q4", "D10", "Q10", "K10"))), Game::fixed_handicap_coords(19, 7));
+        assert_eq!(Some(coords(vec!("D4", "Q16", "D16", "Q4", "D10", "Q10", "K4", "K16"))), Game::fixed_handicap_coords(19, 8));
+        assert_eq!(Some(coords(vec!("D4", "K10", "D10", "K4", "G7"))), Game::fixed_handicap_coords(13, 5));
+        assert_eq!(Some(coords(vec!("C3", "G7", "C7", "G3", "C5", "G5", "E3", "E7", "E5"))), Game::fixed_handicap_coords(9, 9));
+        assert_eq!(Some(coords(vec!("C3", "E5", "C5", "E3"))), Game::fixed_handicap_coords(7, 4));
+        assert_eq!(Some(coords(vec!("D4", "W22", "D22", "W4", "N13"))), Game::fixed_handicap_coords(25, 5));
+        assert_eq!(Some(vec!()), Game::fixed_handicap_coords(19, 0));
+    }
+
+    #[test]
+    fn handicap_is_limited_by_board_size() {
+        assert_eq!(None, Game::fixed_handicap_coords(19, 10));
+        assert_eq!(None, Game::fixed_handicap_coords(7, 5));
+        assert_eq!(None, Game::fixed_handicap_coords(10, 5));
+        assert_eq!(Some(coords(vec!("C3", "H8", "C8", "H3"))), Game::fixed_handicap_coords(10, 4));
+        assert_eq!(None, Game::fixed_handicap_coords(6, 2));
+        assert_eq!(None, Game::fixed_handicap_coords(26, 2));
+        assert_eq!(9, Game::max_fixed_handicap(25));
+        assert_eq!(4, Game::max_fixed_handicap(24));
+    }
+
+    #[test]
+    fn handicap_it_sets_stones_in_place_for_small_boards() {
+        let g = Game::new(13, 0.5, 3);
+        assert_eq!(Color::White, g.next_turn());
+        for c in &["D4", "K10", "D10"] {
+            assert_color(&g, Color::Black, c);
+        }
+        assert_eq!(3, g.board().points(Color::Black).count());
+    }
+
+    #[test]
+    fn pretty_print_marks_the_handicap_points() {
+        let pp = Game::new(9, 0.5, 0).pretty_print();
+        assert!(pp.contains("  7 . . + . + . + . .  7"));
+        assert!(pp.contains("  5 . . + . + . + . .  5"));
+        assert!(pp.contains("  4 . . . . . . . . .  4"));
+    }
+
+    #[test]
+    fn free_handicap_places_stones_anywhere() {
+        let mut g = Game::new(9, 0.5, 0);
+        assert_eq!(Ok(()), g.set_free_handicap(&coords(vec!("A1", "E5", "J9"))));
+        assert_eq!(3, g.handicap());
+        assert_eq!(Color::White, g.next_turn());
+        assert_color(&g, Color::Black, "A1");
+        assert_color(&g, Color::Black, "E5");
+        assert_color(&g, Color::Black, "J9");
+        assert!(g.play(Move::from_str("white c3").unwrap()));
+    }
+
+    #[test]
+    fn free_handicap_fails_on_wrong_placements() {
+        let mut g = Game::new(9, 0.5, 0);
+        assert_eq!(Err(SetupError::InvalidHandicap(1)), g.set_free_handicap(&coords(vec!("A1"))));
+        assert_eq!(Err(SetupError::Duplicated(Coord::from_str("A1").unwrap())), g.set_free_handicap(&coords(vec!("A1", "B2", "A1"))));
+        assert_eq!(Err(SetupError::OutOfBoard(Coord::from_str("K10").unwrap())), g.set_free_handicap(&coords(vec!("A1", "K10"))));
+        assert_eq!(0, g.handicap());
+        assert_eq!(Color::Black, g.next_turn());
+
+        let mut g = Game::new(9, 0.5, 2);
+        assert_eq!(Err(SetupError::BoardNotEmpty), g.set_free_handicap(&coords(vec!("A1", "B2"))));
+    }
+
+    fn replay(sgf :&str) -> Game {
+        let gt = sgf::parse(sgf.to_string()).unwrap();
+        let mut g = Game::new_for_gametree(&gt);
+        for gn in gt.moves() {
+            assert!(g.play_node(gn));
+        }
+        g
+    }
+
+    #[test]
+    fn new_for_gametree_places_free_handicap_stones() {
+        let g = replay("(;SZ[13]KM[0.5]HA[3]AB[cc][jd][dk])");
+        assert_eq!(3, g.handicap());
+        assert_eq!(Color::White, g.next_turn());
+        assert_color(&g, Color::Black, "C11");
+        assert_color(&g, Color::Black, "K10");
+        assert_color(&g, Color::Black, "D3");
+        assert_eq!(3, g.board().points(Color::Black).count());
+    }
+
+    #[test]
+    fn new_for_gametree_uses_fixed_handicap_without_setup_stones() {
+        let g = replay("(;SZ[19]KM[0.5]HA[2])");
+        assert_eq!(Color::White, g.next_turn());
+        assert_color(&g, Color::Black, "D4");
+        assert_color(&g, Color::Black, "Q16");
+    }
+
+    #[test]
+    fn new_for_gametree_spreads_handicap_without_a_fixed_layout() {
+        let g = replay("(;SZ[19]KM[0.5]HA[10])");
+        assert_eq!(10, g.handicap());
+        assert_eq!(10, g.board().points(Color::Black).count());
+        for c in Game::fixed_handicap_coords(19, 9).unwrap() {
+            assert_eq!(Color::Black, g.board().get(&c));
+        }
+        let g = replay("(;SZ[5]KM[0.5]HA[5])");
+        assert_eq!(5, g.board().points(Color::Black).count());
+        assert_color(&g, Color::Black, "C3");
+        assert_eq!(Color::White, g.next_turn());
+    }
+
+    #[test]
+    fn it_finds_the_most_spread_points() {
+        let c3 = Coord::from_str("C3").unwrap();
+        assert_eq!(vec!(c3), Game::most_spread_points(5, &[]));
+        // the ring around, as the corners are out of the hoshi band
+        let ring = Game::most_spread_points(5, &[c3]);
+        assert_eq!(8, ring.len());
+        assert!(ring.iter().all(|c| Game::distance(c, &c3) == 1 && Game::edge_distance(c, 5) == 1));
+        assert_eq!(24, Game::handicap_coords(5, 30).len());
+    }
+
+    #[test]
+    fn new_for_gametree_plays_the_move_in_the_root() {
+        let g = replay("(;SZ[9]B[ee];W[cc])");
+        assert_eq!(2, g.move_count());
+        assert_color(&g, Color::Black, "E5");
+        assert_color(&g, Color::White, "C7");
+        assert_eq!(Color::Black, g.next_turn());
+    }
+
+    #[test]
+    fn replay_reproduces_a_tsumego() {
+        /*
+        *  9 O O . . . . . . .
+        *  8 X X . . . . . . .
+        *  7 X . . . . . . . .
+        *    A B C D E F G H J
+        */
+        let g = replay("(;SZ[9]AB[ab][bb][ac]AW[ba][aa]PL[B];B[ca]C[captures two])");
+        assert_color(&g, Color::Empty, "A9");
+        assert_color(&g, Color::Empty, "B9");
+        assert_color(&g, Color::Black, "C9");
+        assert_eq!(2, g.captured_count(Color::White));
+        assert_eq!(Color::White, g.next_turn());
+    }
+
+    #[test]
+    fn replay_honors_player_and_removals_in_the_middle() {
+        let g = replay("(;SZ[9]AB[ee];AE[ee]AW[cc]PL[W];W[gg];W[gc])");
+        assert_color(&g, Color::Empty, "E5");
+        assert_color(&g, Color::White, "C7");
+        assert_color(&g, Color::White, "G3");
+        assert_color(&g, Color::White, "G7");
+        assert_eq!(2, g.move_count());
+        assert_eq!(Color::Black, g.next_turn());
+    }
+
+    #[test]
+    fn setup_positions_count_for_superko() {
+        let mut g = replay("(;SZ[9]AB[ee])");
+        let mut empty = GameNode::new_empty();
+        empty.add_setup(Move::Stone(Coord::from_str("E5").unwrap(), Color::Empty));
+        g.apply_setup(&empty);
+        // black can't get back to the first position
+        assert!(!g.play(Move::from_str("Black E5").unwrap()));
+    }
+
+    fn stones(moves :Vec<&str>) -> Vec<Move> {
+        moves.into_iter().map(|m| Move::from_str(m).unwrap()).collect()
+    }
+
+    #[test]
+    fn setup_stones_places_and_removes_stones_without_moves() {
+        let mut g = Game::new(9, 5.5, 0);
+        assert!(g.setup_stones(&stones(vec!("Black E5", "White C3", "Black C4"))).is_ok());
+        assert_color(&g, Color::Black, "E5");
+        assert_color(&g, Color::White, "C3");
+        assert_color(&g, Color::Black, "C4");
+        assert!(g.setup_stones(&[Move::Stone(Coord::from_str("E5").unwrap(), Color::Empty)]).is_ok());
+        assert_color(&g, Color::Empty, "E5");
+        assert_eq!(0, g.move_count());
+        assert_eq!(Color::Black, g.next_turn());
+    }
+
+    #[test]
+    fn setup_stones_fails_on_chains_without_liberties() {
+        let mut g = Game::new(9, 5.5, 0);
+        // the white stone in the corner
+        assert_eq!(Err(SetupError::NoLiberties(Coord::from_str("A1").unwrap())),
+                   g.setup_stones(&stones(vec!("White A1", "Black A2", "Black B1"))));
+        // a black chain
+        assert_eq!(Err(SetupError::NoLiberties(Coord::from_str("A1").unwrap())),
+                   g.setup_stones(&stones(vec!("Black A1", "Black B1", "White A2", "White B2", "White C1"))));
+        // nothing changed
+        assert_eq!(0, g.board().points(Color::Black).count() + g.board().points(Color::White).count());
+    }
+
+    #[test]
+    fn setup_stones_fails_on_invalid_stones() {
+        let mut g = Game::new(9, 5.5, 0);
+        assert_eq!(Err(SetupError::OutOfBoard(Coord::from_str("K10").unwrap())), g.setup_stones(&stones(vec!("Black K10"))));
+        assert_eq!(Err(SetupError::NotAStone(Move::Pass(Color::Black))), g.setup_stones(&[Move::Pass(Color::Black)]));
+        let dame = Move::Stone(Coord::from_str("A1").unwrap(), Color::Dame);
+        assert_eq!(Err(SetupError::NotAStone(dame)), g.setup_stones(&[dame]));
+    }
+
+    #[test]
+    fn new_for_position_takes_the_board_and_turn() {
+        let mut b = Board::new(9);
+        b.set_moves(stones(vec!("Black A2", "Black B1", "White C1", "White B2")));
+        let mut g = Game::new_for_position(b, 6.5, Color::White).unwrap();
+        assert_eq!(6.5, g.komi());
+        assert_eq!(Color::White, g.next_turn());
+        assert_color(&g, Color::Black, "B1");
+        assert!(g.play(Move::from_str("White A3").unwrap()));
+        assert!(g.play(Move::from_str("Black C2").unwrap()));
+        assert!(g.play(Move::from_str("White A1").unwrap()));
+        assert_eq!(2, g.captured_count(Color::Black));
+    }
+
+    #[test]
+    fn new_for_position_fails_on_dead_chains() {
+        let mut b = Board::new(9);
+        b.set_moves(stones(vec!("Black A1", "White A2", "White B1")));
+        assert_eq!(Err(SetupError::NoLiberties(Coord::from_str("A1").unwrap())), Game::new_for_position(b, 6.5, Color::White).map(|_| ()));
+    }
+
+    #[test]
+    fn seeded_positions_count_for_superko() {
+        let mut g = Game::new(9, 5.5, 0);
+        let mut before = Board::new(9);
+        before.set_move(Move::from_str("Black E5").unwrap());
+        g.seed_super_ko(&before);
+        assert!(!g.play(Move::from_str("Black E5").unwrap()));
+        assert!(g.play(Move::from_str("Black E4").unwrap()));
+        g.set_next_turn(Color::Black);
+        assert_eq!(Color::Black, g.next_turn());
+    }
+
+    fn assert_same_state(g :&Game, g2 :&Game) {
+        assert_eq!(g.board(), g2.board());
+        assert_eq!(g.captured(), g2.captured());
+        assert_eq!(g.next_turn(), g2.next_turn());
+        assert_eq!(g.finished(), g2.finished());
+        assert_eq!(g.moves(), g2.moves());
+        assert_eq!(g.super_ko, g2.super_ko);
+    }
+
+    #[test]
+    fn undo_takes_back_captures_and_ko() {
+        let mut g = Game::new(19, 5.5, 0);
+        for m in stones(vec!("black a1", "white b1", "black a3", "white c1", "black b2")) {
+            assert!(g.play(m));
+        }
+        let before = g.clone();
+        assert!(g.play(Move::from_str("white a2").unwrap())); // captures a1
+        assert_eq!((1,0), g.captured());
+        assert!(!g.play(Move::from_str("black a1").unwrap())); // ko
+
+        assert_eq!(Some(Move::from_str("white a2").unwrap()), g.undo());
+        assert_same_state(&before, &g);
+        assert_color(&g, Color::Black, "A1");
+        assert_color(&g, Color::Empty, "A2");
+
+        // and the game goes on as if nothing happened
+        assert!(g.play(Move::from_str("white a2").unwrap()));
+        assert!(!g.play(Move::from_str("black a1").unwrap()));
+    }
+
+    #[test]
+    fn undo_takes_back_passes_and_finished_games() {
+        let mut g = Game::new(9, 5.5, 0);
+        assert_eq!(None, g.undo());
+        let before = g.clone();
+        assert!(g.play(Move::Pass(Color::Black)));
+        assert!(g.play(Move::Pass(Color::White)));
+        assert!(g.finished());
+        assert_eq!(Some(Move::Pass(Color::White)), g.undo());
+        assert!(!g.finished());
+        assert_eq!(Some(Move::Pass(Color::Black)), g.undo());
+        assert_same_state(&before, &g);
+        assert_eq!(None, g.undo());
+    }
+
+    #[test]
+    fn undo_everything_after_a_random_game() {
+        for &board_size in &[9, 13, 19] {
+            let mut g = Game::new(board_size, 5.5, 0);
+            let mut history = vec!(g.clone());
+            while !g.finished() && g.move_count() < board_size*board_size {
+                play_random_move(&mut g, board_size);
+                history.push(g.clone());
+            }
+            while let Some(before) = history.pop() {
+                assert_same_state(&before, &g);
+                g.board().assert_chains_consistent();
+                g.undo();
+            }
+            assert_eq!(0, g.move_count());
+        }
+    }
+
+    #[test]
+    fn undo_is_not_possible_beyond_setup() {
+        let mut g = Game::new(9, 5.5, 0);
+        assert!(g.play(Move::from_str("Black E5").unwrap()));
+        assert!(g.setup_stones(&stones(vec!("White C3"))).is_ok());
+        assert_eq!(None, g.undo());
+        assert_color(&g, Color::Black, "E5");
+    }
+
     #[test]
     fn simplest_finished_game() {
         let mut g = Game::new(19, 5.5, 0);
@@ -367,28 +1099,320 @@ mod tests {
         assert!(g.play(white_move2));
     }
 
+    #[test]
+    fn try_play_reports_captures_and_ko() {
+        let mut g = Game::new(19, 5.5, 0);
+        for m in stones(vec!("black a1", "white b1", "black a3", "white c1", "black b2")) {
+            assert!(g.try_play(m).is_ok());
+        }
+        let outcome = g.try_play(Move::from_str("white a2").unwrap()).unwrap();
+        assert_eq!(vec!(Coord::from_str("A1").unwrap()), outcome.captured);
+        assert_eq!(Some(Coord::from_str("A1").unwrap()), outcome.ko);
+
+        let outcome = g.try_play(Move::from_str("black t19").unwrap()).unwrap();
+        assert_eq!(MoveOutcome { captured : vec!(), suicided : vec!(), ko : None }, outcome);
+    }
+
+    #[test]
+    fn try_play_reports_no_ko_when_capturing_with_a_chain() {
+        let mut g = Game::new(9, 5.5, 0);
+        for m in stones(vec!("black a1", "white b1", "black e5", "white a3", "black e6")) {
+            assert!(g.play(m));
+        }
+        let outcome = g.try_play(Move::from_str("white a2").unwrap()).unwrap();
+        assert_eq!(1, outcome.captured.len());
+        assert_eq!(None, outcome.ko);
+    }
+
+    #[test]
+    fn try_play_tells_why_a_move_is_illegal() {
+        let mut g = Game::new(9, 5.5, 0);
+        for m in stones(vec!("black a1", "white b1", "black a3", "white c1", "black b2", "white a2")) {
+            assert!(g.play(m));
+        }
+        assert_eq!(Err(IllegalMove::WrongColor(Color::White)), g.try_play(Move::from_str("white e5").unwrap()));
+        assert_eq!(Err(IllegalMove::Occupied(Coord::from_str("B2").unwrap())), g.try_play(Move::from_str("black b2").unwrap()));
+        assert_eq!(Err(IllegalMove::SuperKo(Coord::from_str("A1").unwrap())), g.try_play(Move::from_str("black a1").unwrap()));
+        assert_eq!(Err(IllegalMove::OutOfBoard(Coord::from_str("K10").unwrap())), g.try_play(Move::from_str("black k10").unwrap()));
+        // refused moves leave the game untouched
+        assert_eq!(6, g.move_count());
+        assert_eq!(Color::Black, g.next_turn());
+
+        for m in stones(vec!("black e5", "white j2", "black e6", "white h1")) {
+            assert!(g.play(m));
+        }
+        assert_eq!(Err(IllegalMove::Suicide(Coord::from_str("J1").unwrap())), g.try_play(Move::from_str("black j1").unwrap()));
+
+        assert!(g.play(Move::Pass(Color::Black)));
+        assert!(g.play(Move::Pass(Color::White)));
+        assert_eq!(Err(IllegalMove::GameFinished), g.try_play(Move::Pass(Color::Black)));
+    }
+
+    #[test]
+    fn rules_come_from_the_gametree() {
+        assert_eq!(Rules::default(), *replay("(;SZ[9])").rules());
+        assert_eq!(Rules::japanese(), *replay("(;SZ[9]RU[Japanese])").rules());
+        assert_eq!(Rules::tromp_taylor(), *replay("(;SZ[9]RU[Tromp-Taylor])").rules());
+    }
+
+    #[test]
+    fn simple_ko_forbids_only_the_immediate_retake() {
+        let mut g = Game::new(9, 6.5, 0);
+        g.set_rules(Rules::japanese());
+        for m in stones(vec!("black a1", "white b1", "black a3", "white c1", "black b2", "white a2")) {
+            assert!(g.play(m));
+        }
+        assert_eq!(Err(IllegalMove::Ko(Coord::from_str("A1").unwrap())), g.try_play(Move::from_str("black a1").unwrap()));
+        // after a threat and an answer it can be taken back
+        for m in stones(vec!("black e5", "white e6", "black a1")) {
+            assert!(g.play(m));
+        }
+        assert_eq!(Err(IllegalMove::Ko(Coord::from_str("A2").unwrap())), g.try_play(Move::from_str("white a2").unwrap()));
+        // and undo brings the ko back
+        g.undo();
+        g.undo();
+        g.undo();
+        assert_eq!(Err(IllegalMove::Ko(Coord::from_str("A1").unwrap())), g.try_play(Move::from_str("black a1").unwrap()));
+    }
+
+    #[test]
+    fn only_superko_rules_forbid_repeating_an_older_position() {
+        let mut board = Board::new(9);
+        board.set_move(Move::from_str("black e5").unwrap());
+        let mut japanese = Game::new(9, 6.5, 0);
+        japanese.set_rules(Rules::japanese());
+        japanese.seed_super_ko(&board);
+        let mut chinese = Game::new(9, 6.5, 0);
+        chinese.seed_super_ko(&board);
+
+        assert!(japanese.play(Move::from_str("black e5").unwrap()));
+        assert_eq!(Err(IllegalMove::SuperKo(Coord::from_str("E5").unwrap())), chinese.try_play(Move::from_str("black e5").unwrap()));
+    }
+
+    #[test]
+    fn multi_stone_suicide_removes_the_chain_when_allowed() {
+        let setup = stones(vec!("black a1", "white c1", "black b1", "white b2", "black e5", "white a3"));
+        let mut g = Game::new(9, 7.5, 0);
+        g.set_rules(Rules::new_zealand());
+        for m in &setup {
+            assert!(g.play(*m));
+        }
+        let before = g.clone();
+        let outcome = g.try_play(Move::from_str("black a2").unwrap()).unwrap();
+        assert_eq!(3, outcome.suicided.len());
+        assert!(outcome.captured.is_empty());
+        for c in &["A1", "B1", "A2"] {
+            assert_color(&g, Color::Empty, c);
+        }
+        // white took them
+        assert_eq!(3, g.captured_count(Color::Black));
+        assert_eq!(Color::White, g.next_turn());
+        // the hash is the one of the position without the chain
+        let mut board = Board::new(9);
+        board.set_moves(stones(vec!("white c1", "white b2", "black e5", "white a3")));
+        assert_eq!(board.zobrist(), g.board().zobrist());
+
+        assert_eq!(Some(Move::from_str("black a2").unwrap()), g.undo());
+        assert_same_state(&before, &g);
+
+        let mut chinese = Game::new(9, 7.5, 0);
+        for m in &setup {
+            assert!(chinese.play(*m));
+        }
+        assert_eq!(Err(IllegalMove::Suicide(Coord::from_str("A2").unwrap())), chinese.try_play(Move::from_str("black a2").unwrap()));
+        assert_eq!(3, chinese.board().points(Color::Black).count());
+    }
+
+    #[test]
+    fn single_stone_suicide_is_captured_by_the_opponent() {
+        let mut g = Game::new(9, 7.5, 0);
+        g.set_rules(Rules { ko : KoRule::Simple, .. Rules::tromp_taylor() });
+        for m in stones(vec!("black e5", "white b1", "black e6", "white a2")) {
+            assert!(g.play(m));
+        }
+        let outcome = g.try_play(Move::from_str("black a1").unwrap()).unwrap();
+        assert_eq!(vec!(Coord::from_str("A1").unwrap()), outcome.suicided);
+        assert_color(&g, Color::Empty, "A1");
+        assert_eq!((1, 0), g.captured());
+
+        // with positional superko it just repeats the position, so it is not allowed
+        let mut tt = Game::new(9, 7.5, 0);
+        tt.set_rules(Rules::tromp_taylor());
+        for m in stones(vec!("black e5", "white b1", "black e6", "white a2")) {
+            assert!(tt.play(m));
+        }
+        assert_eq!(Err(IllegalMove::SuperKo(Coord::from_str("A1").unwrap())), tt.try_play(Move::from_str("black a1").unwrap()));
+        assert_color(&tt, Color::Empty, "A1");
+        assert_eq!((0, 0), tt.captured());
+    }
+
+    #[test]
+    fn situational_superko_allows_the_same_position_with_the_other_player_to_move() {
+        // a single stone suicide gives the same stones, but it is white to move now
+        let mut nz = Game::new(9, 7.5, 0);
+        nz.set_rules(Rules::new_zealand());
+        for m in stones(vec!("black e5", "white b1", "black e6", "white a2")) {
+            assert!(nz.play(m));
+        }
+        let position = nz.position_hash();
+        assert!(nz.play(Move::from_str("black a1").unwrap()));
+        assert_eq!(position, nz.position_hash());
+        assert_eq!((1, 0), nz.captured());
+
+        // but not retaking a ko
+        for m in stones(vec!("white h9", "black j9", "white g9", "black j7", "white e4", "black h8", "white j8")) {
+            assert!(nz.play(m));
+        }
+        assert_eq!(Err(IllegalMove::SuperKo(Coord::from_str("J9").unwrap())), nz.try_play(Move::from_str("black j9").unwrap()));
+    }
+
+    #[test]
+    fn situation_hash_tells_turn_and_passes_apart() {
+        let mut g = Game::new(9, 7.5, 0);
+        let empty_black = g.situation_hash();
+        assert!(g.play(Move::Pass(Color::Black)));
+        let empty_white_after_pass = g.situation_hash();
+        assert_eq!(g.position_hash(), g.board().zobrist());
+        assert!(empty_black != empty_white_after_pass);
+
+        let mut g2 = Game::new(9, 7.5, 0);
+        g2.set_next_turn(Color::White);
+        assert!(g2.situation_hash() != empty_black);
+        assert!(g2.situation_hash() != empty_white_after_pass);
+        assert_eq!(g.position_hash(), g2.position_hash());
+
+        assert!(g.play(Move::from_str("white e5").unwrap()));
+        assert!(g2.play(Move::from_str("white e5").unwrap()));
+        assert_eq!(g.situation_hash(), g2.situation_hash());
+
+        g.undo();
+        assert_eq!(empty_white_after_pass, g.situation_hash());
+    }
+
+    #[test]
+    fn legal_moves_in_an_empty_board_are_all_points_and_pass() {
+        let g = Game::new(9, 7.5, 0);
+        let moves = g.legal_moves();
+        assert_eq!(82, moves.len());
+        assert_eq!(Some(&Move::Pass(Color::Black)), moves.last());
+        assert!(!g.is_legal(Move::Pass(Color::White)));
+        assert!(!g.is_legal(Move::from_str("white e5").unwrap()));
+    }
+
+    #[test]
+    fn legal_moves_leave_out_occupied_suicide_and_ko() {
+        let mut g = Game::new(9, 7.5, 0);
+        for m in stones(vec!("black e5", "white j2", "black e6", "white h1",
+                             "black a1", "white b1", "black a3", "white c1", "black b2", "white a2")) {
+            assert!(g.play(m));
+        }
+        let moves = g.legal_moves();
+        // 81 - 9 stones - a1 (superko) - j1 (suicide) + pass
+        assert_eq!(71, moves.len());
+        assert!(!moves.contains(&Move::from_str("black a1").unwrap()));
+        assert!(!moves.contains(&Move::from_str("black j1").unwrap()));
+        assert!(!moves.contains(&Move::from_str("black e5").unwrap()));
+        assert!(moves.contains(&Move::from_str("black d4").unwrap()));
+
+        g.set_rules(Rules::new_zealand());
+        assert!(g.is_legal(Move::from_str("black j1").unwrap()));
+        assert!(!g.is_legal(Move::from_str("black a1").unwrap()));
+
+        assert!(g.play(Move::Pass(Color::Black)));
+        assert!(g.play(Move::Pass(Color::White)));
+        assert!(g.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn is_legal_agrees_with_try_play_in_random_games() {
+        for rules in &[Rules::chinese(), Rules::japanese(), Rules::new_zealand(), Rules::tromp_taylor()] {
+            let mut g = Game::new(7, 7.5, 0);
+            g.set_rules(*rules);
+            while !g.finished() && g.move_count() < 150 {
+                let before = g.clone();
+                for coord in Coord::all_possibles(7) {
+                    for color in &[Color::Black, Color::White] {
+                        let m = Move::Stone(coord, *color);
+                        let legal = g.is_legal(m);
+                        assert_eq!(legal, g.try_play(m).is_ok(), "{} in {} with {:?}", m, g, rules);
+                        if legal {
+                            g.undo();
+                        }
+                    }
+                }
+                assert_same_state(&before, &g);
+                g.board().assert_chains_consistent();
+                play_random_move(&mut g, 7);
+            }
+        }
+    }
+
     //
     // benchs for vec board
     //
     use test::Bencher;
 
+    fn play_random_move(g :&mut Game, board_size :usize) {
+        let mut count = 0;
+        let turn_color = g.next_turn();
+        loop {
+            let m = Move::Stone( Coord::random(board_size), turn_color);
+            if g.play(m) {
+                break;
+            }
+            count += 1;
+            if count > board_size*2 {
+                g.play(Move::Pass(turn_color));
+                break;
+            }
+        }
+    }
+
     fn play_random_game(board_size :usize) {
         let mut g = Game::new(board_size, 5.5, 0);
         while !g.finished() && g.move_count() < board_size*board_size {
-            let mut count = 0;
-            let turn_color = g.next_turn();
-            loop {
-                let m = Move::Stone( Coord::random(board_size), turn_color);
-                if g.play(m) {
-                    break;
-                }
-                count = count + 1;
-                if count > board_size*2 {
-                    g.play(Move::Pass(turn_color));
-                    break;
+            play_random_move(&mut g, board_size);
+        }
+    }
+
+    fn half_played_game(board_size :usize) -> Game {
+        let mut g = Game::new(board_size, 5.5, 0);
+        while g.move_count() < board_size*board_size/2 {
+            play_random_move(&mut g, board_size);
+        }
+        g
+    }
+
+    #[bench]
+    fn legal_moves_19x19(b: &mut Bencher) {
+        let g = half_played_game(19);
+        b.iter(|| g.legal_moves());
+    }
+
+    #[bench]
+    fn clone_and_play_all_19x19(b: &mut Bencher) {
+        let g = half_played_game(19);
+        let all = Coord::all_possibles(19);
+        b.iter(|| {
+            for coord in &all {
+                let mut g2 = g.clone();
+                g2.play(Move::Stone(*coord, g2.next_turn()));
+            }
+        })
+    }
+
+    #[bench]
+    fn play_and_undo_all_19x19(b: &mut Bencher) {
+        let mut g = half_played_game(19);
+        let all = Coord::all_possibles(19);
+        b.iter(|| {
+            for coord in &all {
+                let turn = g.next_turn();
+                if g.play(Move::Stone(*coord, turn)) {
+                    g.undo();
                 }
             }
-        }
+        })
     }
 
     #[bench]
@@ -25,6 +25,17 @@ pub struct GameResultRange {
 
 impl GameResult {
 
+    /// the result of a count, positive when white leads and negative when black does; zero is jigo.
+    pub fn from_score(white_minus_black :f32) -> GameResult {
+        if white_minus_black > 0.0 {
+            GameResult::Score(Color::White, white_minus_black)
+        } else if white_minus_black < 0.0 {
+            GameResult::Score(Color::Black, -white_minus_black)
+        } else {
+            GameResult::Draw
+        }
+    }
+
     pub fn is_score(&self) -> bool {
         match self {
             &GameResult::Score(_,_) => true,
@@ -271,6 +282,13 @@ mod tests {
         assert_eq!(GameResult::Unknown, GameResult::Unknown);
     }
 
+    #[test]
+    fn it_from_score() {
+        assert_eq!(GameResult::Score(Color::White, 6.5), GameResult::from_score(6.5));
+        assert_eq!(GameResult::Score(Color::Black, 2.0), GameResult::from_score(-2.0));
+        assert_eq!(GameResult::Draw, GameResult::from_score(0.0));
+    }
+
     #[test]
     fn it_game_result_range_eq() {
         assert_eq!( GameResultRange::new(GameResult::Score(Color::Black, 5.0), 10), GameResultRange::new(GameResult::Score(Color::Black, 5.0), 10));
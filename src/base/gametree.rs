@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
 
 use base::moves::*;
+use base::color::*;
 use base::game_result::*;
 use base::rank::*;
+use base::rules::*;
 
 #[derive(Clone, Debug)]
 pub struct GameTree {
@@ -12,15 +15,22 @@ pub struct GameTree {
     board_size  :usize,
     handicap    :u16,
     komi        :f32,
+    rules       :Option<Rules>,
     result      :GameResult,
+    root        :GameNode,
     moves       :Vec<GameNode>
 }
 
+/// A node in the game, usually a move. Variants are alternative lines to the nodes following this
+/// one, the first line is the one most commonly used so `variants()` returns it.
 #[derive(Clone, Debug)]
 pub struct GameNode {
-    themove       :Move,
+    themove     :Option<Move>,
     comment     :String,
-    variants    :Vec<GameNode>
+    variants    :Vec<Vec<GameNode>>,
+    setup       :Vec<Move>,
+    player      :Option<Color>,
+    properties  :BTreeMap<String, Vec<String>>
 }
 
 
@@ -35,7 +45,9 @@ impl GameTree {
             board_size: 0,
             handicap: 0,
             komi: 0.0,
+            rules: None,
             result: GameResult::Unknown,
+            root: GameNode::new_empty(),
             moves :Vec::new()
         }
     }
@@ -61,9 +73,18 @@ impl GameTree {
     pub fn set_handicap(&mut self, handicap :u16) { self.handicap = handicap }
     pub fn handicap(&self) -> u16 { self.handicap }
 
+    /// the rules from RU, None when they are not given or not known.
+    pub fn set_rules(&mut self, rules :Option<Rules>) { self.rules = rules }
+    pub fn rules(&self) -> Option<Rules> { self.rules }
+
     pub fn set_result(&mut self, result :GameResult) { self.result = result }
     pub fn result(&self) -> &GameResult { &self.result }
 
+    /// the node before the first move, it holds the game comment and variants starting at move one.
+    pub fn root(&self) -> &GameNode { &self.root }
+    pub fn root_as_mut(&mut self) -> &mut GameNode { &mut self.root }
+    pub fn set_root(&mut self, root :GameNode) { self.root = root }
+
     pub fn moves(&self) -> &Vec<GameNode> { &self.moves }
     pub fn moves_as_mut<'r>(&'r mut self) -> &'r mut Vec<GameNode> { &mut self.moves }
 
@@ -76,21 +97,66 @@ impl GameTree {
 impl GameNode {
 
     pub fn new_simple(m :Move) -> GameNode {
-        GameNode { themove: m, comment: String::new(), variants: Vec::with_capacity(0) }
+        GameNode { themove: Some(m), comment: String::new(), variants: Vec::with_capacity(0),
+                   setup: Vec::with_capacity(0), player: None, properties: BTreeMap::new() }
     }
 
     pub fn new(m :Move, s :&str) -> GameNode {
-        GameNode { themove: m, comment: s.to_string(), variants: Vec::with_capacity(0) }
+        GameNode { themove: Some(m), comment: s.to_string(), variants: Vec::with_capacity(0),
+                   setup: Vec::with_capacity(0), player: None, properties: BTreeMap::new() }
     }
 
+    /// a node without a move, i.e. the root node or a node just commenting the position.
+    pub fn new_empty() -> GameNode {
+        GameNode { themove: None, comment: String::new(), variants: Vec::with_capacity(0),
+                   setup: Vec::with_capacity(0), player: None, properties: BTreeMap::new() }
+    }
+
+    /// pushes the node into the last variant line, creating it if there is none.
     pub fn push(& mut self, gn :GameNode) {
-        self.variants.push(gn)
+        if self.variants.is_empty() {
+            self.variants.push(vec!());
+        }
+        self.variants.last_mut().unwrap().push(gn)
     }
 
-    pub fn themove(&self) -> Move { self.themove }
+    /// adds a new alternative line to the nodes following this one.
+    pub fn push_variant(&mut self, line :Vec<GameNode>) {
+        self.variants.push(line)
+    }
+
+    /// the move played in the node, none in the root, setup or comment only nodes.
+    pub fn themove(&self) -> Option<Move> { self.themove }
+    pub fn set_move(&mut self, m :Move) { self.themove = Some(m) }
+    pub fn set_comment(&mut self, comment :String) { self.comment = comment }
     pub fn comment(&self) -> &String { &self.comment }
-    pub fn variants(&self) -> &Vec<GameNode> { &self.variants }
+    pub fn variants(&self) -> &[GameNode] {
+        match self.variants.first() {
+            Some(line) => line,
+            None       => &[]
+        }
+    }
+    pub fn variant_lines(&self) -> &Vec<Vec<GameNode>> { &self.variants }
     pub fn has_variants(&self) -> bool { !&self.variants.is_empty() }
+
+    /// setup stones (SGF's AB, AW and AE) are not moves: nothing gets captured. Removed stones are
+    /// set as Color::Empty.
+    pub fn setup(&self) -> &Vec<Move> { &self.setup }
+    pub fn add_setup(&mut self, m :Move) { self.setup.push(m) }
+    pub fn has_setup(&self) -> bool { !self.setup.is_empty() || self.player.is_some() }
+
+    /// who plays next after this node (SGF's PL)
+    pub fn player(&self) -> Option<Color> { self.player }
+    pub fn set_player(&mut self, player :Color) { self.player = Some(player) }
+
+    /// properties not decoded into the node or the game tree (i.e. EV, GN, LB, TR, BL...), the
+    /// values are kept unescaped as they were in the SGF.
+    pub fn properties(&self) -> &BTreeMap<String, Vec<String>> { &self.properties }
+    pub fn property(&self, id :&str) -> Option<&Vec<String>> { self.properties.get(id) }
+    pub fn add_property(&mut self, id :&str, value :String) {
+        self.properties.entry(id.to_string()).or_default().push(value)
+    }
+    pub fn remove_property(&mut self, id :&str) -> Option<Vec<String>> { self.properties.remove(id) }
 }
 
 // *********************************************************************************************
@@ -102,10 +168,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use base::moves::*;
-    use base::color::*;
-    use base::rank::*;
-    use base::game_result::*;
+    use base::coord::*;
 
     fn amove(s :&str) -> Move {
         Move::from_str(s).unwrap()
@@ -140,7 +203,7 @@ mod tests {
 
         assert_eq!(1, gt.moves.len());
         let gn = gt.moves().first().unwrap();
-        assert_eq!(amove("Black A1"), gn.themove());
+        assert_eq!(Some(amove("Black A1")), gn.themove());
         assert_eq!(&String::new(), gn.comment());
         assert_eq!(0, gn.variants().len());
     }
@@ -152,7 +215,7 @@ mod tests {
 
         assert_eq!(1, gt.moves.len());
         let gn = gt.moves().first().unwrap();
-        assert_eq!(amove("Black A1"), gn.themove());
+        assert_eq!(Some(amove("Black A1")), gn.themove());
         assert_eq!(&"a good move", gn.comment());
         assert_eq!(0, gn.variants().len());
     }
@@ -177,10 +240,10 @@ mod tests {
         // normal path
         {
             let mut iter = gt.moves().iter();
-            assert_eq!(amove("Black A1"), iter.next().unwrap().themove() );
-            assert_eq!(amove("White A2"), iter.next().unwrap().themove() );
-            assert_eq!(amove("Black A3"), iter.next().unwrap().themove() );
-            assert_eq!(amove("White A4"), iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("Black A1")), iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("White A2")), iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("Black A3")), iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("White A4")), iter.next().unwrap().themove() );
             assert!( iter.next().is_none() );
         }
 
@@ -198,12 +261,82 @@ mod tests {
             let mut iter = gt.moves().iter();
             let gn = iter.nth(1).unwrap();
             let mut gn_iter = gn.variants().iter();
-            assert_eq!(amove("Black B3"),  gn_iter.next().unwrap().themove() );
-            assert_eq!(amove("White B4"),  gn_iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("Black B3")),  gn_iter.next().unwrap().themove() );
+            assert_eq!(Some(amove("White B4")),  gn_iter.next().unwrap().themove() );
             assert!( gn_iter.next().is_none() );
         }
     }
 
+    #[test]
+    fn it_can_push_more_than_one_variant_line() {
+        // B-A1 -> W-A2 -> B-A3
+        //             |-> B-B3 -> W-B4
+        //             \-> B-C3
+        let mut gt = GameTree::new();
+        gt.push( GameNode::new_simple(amove("Black A1")) );
+        gt.push( GameNode::new_simple(amove("White A2")) );
+        gt.push( GameNode::new_simple(amove("Black A3")) );
+        {
+            let sub_node = &mut gt.moves_as_mut()[1];
+            sub_node.push_variant(vec!(GameNode::new_simple(amove("Black B3")), GameNode::new_simple(amove("White B4"))));
+            sub_node.push_variant(vec!(GameNode::new_simple(amove("Black C3"))));
+        }
+
+        let gn = &gt.moves()[1];
+        assert!(gn.has_variants());
+        assert_eq!(2, gn.variant_lines().len());
+        assert_eq!(2, gn.variants().len());
+        assert_eq!(Some(amove("Black B3")), gn.variants()[0].themove());
+        assert_eq!(Some(amove("Black C3")), gn.variant_lines()[1][0].themove());
+    }
+
+    #[test]
+    fn it_keeps_generic_properties() {
+        let mut gn = GameNode::new_simple(amove("Black A1"));
+        assert!(gn.properties().is_empty());
+        gn.add_property("TR", "aa".to_string());
+        gn.add_property("TR", "bb".to_string());
+        gn.add_property("GN", "a game".to_string());
+
+        assert_eq!(2, gn.properties().len());
+        assert_eq!(&vec!("aa".to_string(), "bb".to_string()), gn.property("TR").unwrap());
+        assert_eq!(&vec!("a game".to_string()), gn.property("GN").unwrap());
+        assert!(gn.property("LB").is_none());
+
+        assert_eq!(Some(vec!("a game".to_string())), gn.remove_property("GN"));
+        assert!(gn.property("GN").is_none());
+    }
+
+    #[test]
+    fn it_keeps_setup_stones_and_player() {
+        let mut gn = GameNode::new_empty();
+        assert!(!gn.has_setup());
+        gn.add_setup(amove("Black D4"));
+        gn.add_setup(Move::Stone(Coord::from_str("Q16").unwrap(), Color::Empty));
+        assert!(gn.has_setup());
+        assert_eq!(2, gn.setup().len());
+        assert_eq!(Color::Empty, gn.setup()[1].color());
+        assert!(gn.player().is_none());
+
+        let mut gn2 = GameNode::new_empty();
+        gn2.set_player(Color::White);
+        assert!(gn2.has_setup());
+        assert_eq!(Some(Color::White), gn2.player());
+    }
+
+    #[test]
+    fn it_has_an_empty_root_node() {
+        let gt = GameTree::new();
+        assert_eq!(None, gt.root().themove());
+        assert!(!gt.root().has_variants());
+        assert_eq!(0, gt.root().variants().len());
+    }
+
+    #[test]
+    fn empty_nodes_have_no_move() {
+        assert_eq!(None, GameNode::new_empty().themove());
+    }
+
 
 
 }
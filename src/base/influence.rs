@@ -0,0 +1,140 @@
+use std::cmp;
+
+use base::board::*;
+use base::color::*;
+use base::coord::*;
+
+/// The influence a stone starts with, as in Zobrist's model.
+const STONE_INFLUENCE : i32 = 128;
+
+/// Bouzy's influence of the stones on a board: dilations spread it over the points next to only
+/// one color, then erosions eat it back where the colors meet, so what is left is what each
+/// color holds. Positive is black and negative white.
+pub struct Influence {
+    size   :usize,
+    values :Vec<i32>
+}
+
+impl Influence {
+
+    /// Bouzy's 5/21: 5 dilations and 21 erosions, which leaves about the territories.
+    pub fn bouzy_5_21(board :&Board) -> Influence {
+        Influence::bouzy(board, 5, 21)
+    }
+
+    pub fn bouzy(board :&Board, dilations :usize, erosions :usize) -> Influence {
+        let size = board.size() as usize;
+        let index = |c :&Coord| c.row as usize * size + c.col as usize;
+        let mut values = vec![0; size * size];
+        for c in &board.points(Color::Black) {
+            values[index(&c)] = STONE_INFLUENCE;
+        }
+        for c in &board.points(Color::White) {
+            values[index(&c)] = -STONE_INFLUENCE;
+        }
+        let neighbours : Vec<Vec<usize>> = (0..size * size)
+            .map(|i| Coord::new_us(i / size, i % size).adjacents(size as u8).iter().map(&index).collect())
+            .collect();
+
+        for _ in 0..dilations {
+            values = dilate(&values, &neighbours);
+        }
+        for _ in 0..erosions {
+            values = erode(&values, &neighbours);
+        }
+        Influence { size, values }
+    }
+
+    pub fn get(&self, coord :&Coord) -> i32 {
+        self.values[coord.row as usize * self.size + coord.col as usize]
+    }
+
+    /// the color holding the point, Empty if none does.
+    pub fn owner(&self, coord :&Coord) -> Color {
+        match self.get(coord) {
+            v if v > 0 => Color::Black,
+            v if v < 0 => Color::White,
+            _          => Color::Empty
+        }
+    }
+}
+
+/// points not touching the other color grow by their neighbours of their own.
+fn dilate(values :&[i32], neighbours :&[Vec<usize>]) -> Vec<i32> {
+    values.iter().zip(neighbours).map(|(&v, ns)| {
+        if v >= 0 && ns.iter().all(|&n| values[n] >= 0) {
+            v + ns.iter().filter(|&&n| values[n] > 0).count() as i32
+        } else if v <= 0 && ns.iter().all(|&n| values[n] <= 0) {
+            v - ns.iter().filter(|&&n| values[n] < 0).count() as i32
+        } else {
+            v
+        }
+    }).collect()
+}
+
+/// points shrink by their neighbours not of their own, down to zero.
+fn erode(values :&[i32], neighbours :&[Vec<usize>]) -> Vec<i32> {
+    values.iter().zip(neighbours).map(|(&v, ns)| {
+        if v > 0 {
+            cmp::max(0, v - ns.iter().filter(|&&n| values[n] <= 0).count() as i32)
+        } else if v < 0 {
+            cmp::min(0, v + ns.iter().filter(|&&n| values[n] >= 0).count() as i32)
+        } else {
+            0
+        }
+    }).collect()
+}
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+    use base::moves::*;
+
+    fn board_with(size :usize, stones :&[(&str, Color)]) -> Board {
+        let mut board = Board::new(size);
+        for &(c, color) in stones {
+            board.set_move(Move::Stone(Coord::from_str(c).unwrap(), color));
+        }
+        board
+    }
+
+    /// black on C1-C4 and white on G1-G4 of a 9x9.
+    fn low_walls() -> Board {
+        board_with(9, &[("C1", Color::Black), ("C2", Color::Black), ("C3", Color::Black), ("C4", Color::Black),
+                        ("G1", Color::White), ("G2", Color::White), ("G3", Color::White), ("G4", Color::White)])
+    }
+
+    #[test]
+    fn walls_hold_what_is_behind() {
+        let influence = Influence::bouzy_5_21(&low_walls());
+        for c in &["A1", "B4", "C1"] {
+            assert_eq!(Color::Black, influence.owner(&Coord::from_str(c).unwrap()), "{}", c);
+        }
+        for c in &["J1", "H4", "G2"] {
+            assert_eq!(Color::White, influence.owner(&Coord::from_str(c).unwrap()), "{}", c);
+        }
+        // in between and above it's no one's yet
+        for c in &["E2", "D3", "A9", "B6", "E5"] {
+            assert_eq!(Color::Empty, influence.owner(&Coord::from_str(c).unwrap()), "{}", c);
+        }
+    }
+
+    #[test]
+    fn lone_stones_hold_only_themselves() {
+        let board = board_with(19, &[("D4", Color::Black), ("Q16", Color::White), ("D16", Color::Black), ("Q4", Color::White)]);
+        let influence = Influence::bouzy_5_21(&board);
+        let held = Coord::all_possibles(19).into_iter().filter(|c| influence.get(c) != 0).count();
+        assert_eq!(4, held);
+        // but dilations alone spread their influence
+        let influence = Influence::bouzy(&board, 5, 0);
+        assert!(influence.get(&Coord::from_str("D6").unwrap()) > 0);
+        assert!(influence.get(&Coord::from_str("Q14").unwrap()) < 0);
+        assert_eq!(0, influence.get(&Coord::from_str("K10").unwrap()));
+    }
+}
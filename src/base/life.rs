@@ -0,0 +1,340 @@
+use base::board::*;
+use base::bitboard::*;
+use base::color::*;
+use base::coord::*;
+
+/// The points of the chains of the given color which are alive whatever the opponent does, even
+/// if their owner always passes (Benson's algorithm). Chains need two regions enclosed by them
+/// where every empty point is one of their liberties (so the opponent can never play there
+/// without suicide), and those regions must not be enclosed by chains which can die.
+pub fn unconditionally_alive(board :&Board, color :Color) -> BitBoard {
+    let size = board.size();
+    // (stones, liberties, still alive)
+    let mut chains : Vec<(BitBoard, BitBoard, bool)> = board.chains()
+        .filter(|c| c.color() == color)
+        .map(|c| (c.points(), c.liberties(), true))
+        .collect();
+    // (the stones around, empty points, still safe)
+    let mut regions : Vec<(BitBoard, BitBoard, bool)> = vec!();
+    let mut rest = !board.points(color);
+    while let Some(curr) = rest.first() {
+        let region = BitBoard::from_coords(size, &[curr]).flood(&rest);
+        rest = rest - region;
+        regions.push((region.adjacents(), region & board.points(Color::Empty), true));
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // chains with less than two vital regions can be captured ...
+        for chain in chains.iter_mut().filter(|c| c.2) {
+            let vital = regions.iter()
+                .filter(|r| r.2 && !(r.0 & chain.0).is_empty() && (r.1 - chain.1).is_empty())
+                .count();
+            if vital < 2 {
+                chain.2 = false;
+                changed = true;
+            }
+        }
+        // ... so the regions they enclose are no longer safe
+        for region in regions.iter_mut().filter(|r| r.2) {
+            if chains.iter().any(|c| !c.2 && !(c.0 & region.0).is_empty()) {
+                region.2 = false;
+                changed = true;
+            }
+        }
+    }
+
+    chains.iter().filter(|c| c.2).fold(BitBoard::new(size), |alive, c| alive | c.0)
+}
+
+/// Chains of both colors living together as none can fill their shared liberties without being
+/// captured, and all their other liberties are in their own eyes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Seki {
+    pub stones :BitBoard,
+    pub shared_liberties :BitBoard
+}
+
+/// every seki on a board, e.g. to tell which points are no territory under territory scoring.
+pub fn seki(board :&Board) -> Vec<Seki> {
+    let size = board.size();
+    let black = board.points(Color::Black);
+    let white = board.points(Color::White);
+    let empty = board.points(Color::Empty);
+
+    let mut shared = BitBoard::new(size);
+    for p in &(black.dilate() & white.dilate() & empty) {
+        if is_self_atari(board, p, Color::Black) && is_self_atari(board, p, Color::White) {
+            shared.insert(&p);
+        }
+    }
+    if shared.is_empty() {
+        return vec!()
+    }
+
+    // empty regions next to only one color, where the other can't play for long
+    let mut own_black = BitBoard::new(size);
+    let mut own_white = BitBoard::new(size);
+    let mut rest = empty;
+    while let Some(curr) = rest.first() {
+        let region = BitBoard::from_coords(size, &[curr]).flood(&rest);
+        rest = rest - region;
+        let around = region.adjacents();
+        if (around - black).is_empty() {
+            own_black = own_black | region;
+        } else if (around - white).is_empty() {
+            own_white = own_white | region;
+        }
+    }
+
+    // chains with liberties out of the shared ones and their eyes are not in seki, nor are the
+    // liberties they share with others.
+    let mut stones;
+    loop {
+        stones = BitBoard::new(size);
+        let mut dropped = false;
+        for chain in board.chains() {
+            let libs = chain.liberties();
+            if (libs & shared).is_empty() {
+                continue
+            }
+            let own = if chain.color() == Color::Black { own_black } else { own_white };
+            if (libs - shared - own).is_empty() {
+                stones = stones | chain.points();
+            } else {
+                shared = shared - libs;
+                dropped = true;
+            }
+        }
+        if !dropped {
+            break
+        }
+    }
+
+    let mut sekis = vec!();
+    let mut rest = shared;
+    while let Some(curr) = rest.first() {
+        let connected = BitBoard::from_coords(size, &[curr]).flood(&(stones | shared));
+        rest = rest - connected;
+        sekis.push(Seki {
+            stones : connected & stones,
+            shared_liberties : connected & shared
+        });
+    }
+    sekis
+}
+
+/// true if a stone at point would be left with a liberty at most, and captures nothing; worked
+/// out from the chains around without playing it.
+fn is_self_atari(board :&Board, point :Coord, color :Color) -> bool {
+    let adjacents = point.adjacents(board.size());
+    let opposite = color.opposite();
+    if adjacents.iter().any(|a| board.get(a) == opposite && board.chain_at(a).is_some_and(|c| c.in_atari())) {
+        return false
+    }
+    let mut liberties = BitBoard::new(board.size());
+    for a in &adjacents {
+        let adjacent = board.get(a);
+        if adjacent == Color::Empty {
+            liberties.insert(a);
+        } else if adjacent == color {
+            liberties = liberties | board.chain_at(a).map_or(BitBoard::new(board.size()), |c| c.liberties());
+        }
+    }
+    liberties.remove(&point);
+    liberties.count() <= 1
+}
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+    use base::moves::*;
+
+    fn board_with(size :usize, stones :&[(&str, Color)]) -> Board {
+        let mut board = Board::new(size);
+        for &(c, color) in stones {
+            board.set_move(Move::Stone(Coord::from_str(c).unwrap(), color));
+        }
+        board
+    }
+
+    /// a black group along the bottom of a 9x9 with eyes at A1 and C1 (and whatever is at E1).
+    fn two_eyes(extra :&[(&str, Color)]) -> Board {
+        let mut stones = vec!(("B1", Color::Black), ("D1", Color::Black), ("A2", Color::Black), ("B2", Color::Black),
+                              ("C2", Color::Black), ("D2", Color::Black), ("E2", Color::Black), ("F2", Color::Black),
+                              ("F1", Color::Black));
+        stones.extend_from_slice(extra);
+        board_with(9, &stones)
+    }
+
+    #[test]
+    fn two_eyes_are_alive() {
+        let board = two_eyes(&[("E1", Color::Black)]);
+        let alive = unconditionally_alive(&board, Color::Black);
+        assert_eq!(board.points(Color::Black), alive);
+        assert!(unconditionally_alive(&board, Color::White).is_empty());
+    }
+
+    #[test]
+    fn a_single_eye_is_not() {
+        let board = two_eyes(&[("E1", Color::Black), ("C1", Color::Black)]);
+        assert!(unconditionally_alive(&board, Color::Black).is_empty());
+    }
+
+    #[test]
+    fn open_groups_are_not() {
+        let board = board_with(9, &[("D4", Color::Black), ("E4", Color::Black), ("F4", Color::Black)]);
+        assert!(unconditionally_alive(&board, Color::Black).is_empty());
+    }
+
+    #[test]
+    fn big_eyes_are_vital_if_all_their_points_are_liberties() {
+        // A1 and the C1-D1 eye
+        let board = board_with(9, &[("B1", Color::Black), ("A2", Color::Black), ("B2", Color::Black), ("C2", Color::Black),
+                                    ("D2", Color::Black), ("E2", Color::Black), ("E1", Color::Black)]);
+        assert_eq!(board.points(Color::Black), unconditionally_alive(&board, Color::Black));
+        // A1 and a 3x4 eye, which middle points are no liberties
+        let mut stones = vec!(("A2", Color::Black), ("B1", Color::Black), ("B2", Color::Black));
+        for c in &["C1", "C2", "C3", "C4", "C5", "D5", "E5", "F5", "G5", "G4", "G3", "G2", "G1"] {
+            stones.push((c, Color::Black));
+        }
+        let board = board_with(9, &stones);
+        assert!(unconditionally_alive(&board, Color::Black).is_empty());
+    }
+
+    #[test]
+    fn dead_stones_inside_eyes_do_not_matter() {
+        let board = two_eyes(&[("E1", Color::Black), ("A1", Color::White)]);
+        assert_eq!(board.points(Color::Black), unconditionally_alive(&board, Color::Black));
+    }
+
+    #[test]
+    fn eyes_next_to_chains_which_can_die_are_not_safe() {
+        // C1 is an eye for both the B2 chain and D1, but D1 has no other
+        let board = board_with(9, &[("B1", Color::Black), ("A2", Color::Black), ("B2", Color::Black),
+                                    ("C2", Color::Black), ("D1", Color::Black), ("D2", Color::White)]);
+        let alive = unconditionally_alive(&board, Color::Black);
+        assert!(alive.is_empty(), "{}", alive);
+        let board = board_with(9, &[("B1", Color::Black), ("A2", Color::Black), ("B2", Color::Black),
+                                    ("C2", Color::Black), ("D1", Color::Black), ("D2", Color::Black)]);
+        assert_eq!(board.points(Color::Black), unconditionally_alive(&board, Color::Black));
+    }
+
+    fn board_from(rows :&[&str]) -> Board {
+        let size = rows.len();
+        let mut board = Board::new(size);
+        for (r, line) in rows.iter().enumerate() {
+            for (c, ch) in line.split_whitespace().enumerate() {
+                let color = match ch { "X" => Color::Black, "O" => Color::White, _ => Color::Empty };
+                board.set_move(Move::Stone(Coord::new_us(size - r - 1, c), color));
+            }
+        }
+        board
+    }
+
+    fn coords(cs :&[&str]) -> BitBoard {
+        BitBoard::from_coords(9, &cs.iter().map(|c| Coord::from_str(c).unwrap()).collect::<Vec<Coord>>())
+    }
+
+    #[test]
+    fn it_finds_seki_without_eyes() {
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "O O O O O . . . .",
+                                 "X X X O . . . . .",
+                                 "X O X X O . . . .",
+                                 ". O . X O . . . ."]);
+        let sekis = seki(&board);
+        assert_eq!(1, sekis.len());
+        assert_eq!(coords(&["A1", "C1"]), sekis[0].shared_liberties);
+        assert_eq!(coords(&["A3", "B3", "C3", "A2", "C2", "D2", "D1", "B2", "B1"]), sekis[0].stones);
+    }
+
+    #[test]
+    fn it_finds_seki_with_an_eye_each() {
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "O O O O O . . . .",
+                                 "X X X X O O O O .",
+                                 "O O . X X X X O .",
+                                 ". O X X . X X O ."]);
+        let sekis = seki(&board);
+        assert_eq!(1, sekis.len());
+        assert_eq!(coords(&["C2"]), sekis[0].shared_liberties);
+        assert_eq!(15, sekis[0].stones.count());
+        assert!(sekis[0].stones.contains(&Coord::from_str("B1").unwrap()));
+        assert!(!sekis[0].stones.contains(&Coord::from_str("A4").unwrap()));
+    }
+
+    #[test]
+    fn races_to_capture_are_no_seki() {
+        // like the seki with eyes, but black has an outside liberty at J2 instead of an eye
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "O O O O O . . . .",
+                                 "X X X X O O O O .",
+                                 "O O . X X X X X .",
+                                 ". O X X X X X O ."]);
+        assert!(seki(&board).is_empty());
+        // or a bigger eye, so it wins the race
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "O O O O O . . . .",
+                                 "X X X X O O O O .",
+                                 "O O . X X X X O .",
+                                 ". O X X . . X O ."]);
+        assert!(seki(&board).is_empty());
+        // and a single liberty shared by two living groups is just dame
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "X X X X . O O O O",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . ."]);
+        assert!(seki(&board).is_empty());
+    }
+
+    #[test]
+    fn self_atari_is_told_from_the_chains_around() {
+        let board = board_from(&[". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 ". . . . . . . . .",
+                                 "O O . . . . . . .",
+                                 "X X O . . . . . .",
+                                 ". X O . . . . . ."]);
+        let at = |c| Coord::from_str(c).unwrap();
+        // white fills black's last liberty and captures
+        assert!(!is_self_atari(&board, at("A1"), Color::White));
+        // black connects into a chain left with one liberty
+        assert!(is_self_atari(&board, at("A1"), Color::Black));
+        // a lone stone with two liberties
+        assert!(!is_self_atari(&board, at("E5"), Color::Black));
+        // next to white stones but with two liberties of its own
+        assert!(!is_self_atari(&board, at("C3"), Color::Black));
+    }
+}
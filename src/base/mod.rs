@@ -1,10 +1,14 @@
 pub mod board;
+pub mod bitboard;
 pub mod game;
 pub mod coord;
 pub mod moves;
 pub mod color;
 pub mod rank;
 pub mod game_result;
+pub mod rules;
+pub mod life;
+pub mod influence;
 pub mod gametree;
 pub mod sgf;
 pub mod scorer;
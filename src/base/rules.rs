@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use std::fmt::{Formatter, Error, Display};
+
+/// How repeating a previous position is forbidden.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KoRule {
+    /// only the immediate recapture of a single stone ko is forbidden
+    Simple,
+    /// no move can recreate a previous board position
+    PositionalSuperko,
+    /// no move can recreate a previous board position with the same player to move
+    SituationalSuperko
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ScoringMethod {
+    /// stones plus surrounded empty points
+    Area,
+    /// surrounded empty points plus prisoners
+    Territory
+}
+
+/// Points given to white for the handicap stones, on top of komi.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HandicapCompensation {
+    None,
+    /// one point per handicap stone
+    PerStone,
+    /// one point per handicap stone after the first
+    PerStoneButFirst
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rules {
+    pub suicide : bool,
+    pub ko : KoRule,
+    pub scoring : ScoringMethod,
+    pub handicap_compensation : HandicapCompensation
+}
+
+impl Rules {
+
+    pub fn japanese() -> Self {
+        Rules {
+            suicide : false,
+            ko : KoRule::Simple,
+            scoring : ScoringMethod::Territory,
+            handicap_compensation : HandicapCompensation::None
+        }
+    }
+
+    pub fn chinese() -> Self {
+        Rules {
+            suicide : false,
+            ko : KoRule::PositionalSuperko,
+            scoring : ScoringMethod::Area,
+            handicap_compensation : HandicapCompensation::PerStone
+        }
+    }
+
+    pub fn aga() -> Self {
+        Rules {
+            suicide : false,
+            ko : KoRule::SituationalSuperko,
+            scoring : ScoringMethod::Area,
+            handicap_compensation : HandicapCompensation::PerStoneButFirst
+        }
+    }
+
+    pub fn new_zealand() -> Self {
+        Rules {
+            suicide : true,
+            ko : KoRule::SituationalSuperko,
+            scoring : ScoringMethod::Area,
+            handicap_compensation : HandicapCompensation::None
+        }
+    }
+
+    pub fn tromp_taylor() -> Self {
+        Rules {
+            suicide : true,
+            ko : KoRule::PositionalSuperko,
+            scoring : ScoringMethod::Area,
+            handicap_compensation : HandicapCompensation::None
+        }
+    }
+
+    /// The points white gets for the given number of handicap stones.
+    pub fn compensation(&self, handicap :u16) -> f32 {
+        match self.handicap_compensation {
+            HandicapCompensation::None => 0.0,
+            HandicapCompensation::PerStone => handicap as f32,
+            HandicapCompensation::PerStoneButFirst => if handicap > 1 { (handicap - 1) as f32 } else { 0.0 }
+        }
+    }
+
+    /// The SGF RU name, if these are one of the known rulesets.
+    pub fn name(&self) -> Option<&'static str> {
+        if *self == Rules::japanese() {
+            Some("Japanese")
+        } else if *self == Rules::chinese() {
+            Some("Chinese")
+        } else if *self == Rules::aga() {
+            Some("AGA")
+        } else if *self == Rules::new_zealand() {
+            Some("NZ")
+        } else if *self == Rules::tromp_taylor() {
+            Some("Tromp-Taylor")
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Rules {
+    /// Chinese rules, for games which don't say. Before there were rules the scorers counted
+    /// captures on top of stones and territory, without handicap compensation, so games without
+    /// RU now score by area and white gets a point per handicap stone.
+    fn default() -> Self {
+        Rules::chinese()
+    }
+}
+
+impl FromStr for Rules {
+
+    type Err = RulesParseError;
+
+    fn from_str(s: &str) -> Result<Rules, Self::Err> {
+        let su = s.trim().to_lowercase().replace(['_', '-'], " ");
+        match su.as_str() {
+            "japanese" | "jp" | "japan" => Ok(Rules::japanese()),
+            "chinese" | "cn" | "china" => Ok(Rules::chinese()),
+            "aga" => Ok(Rules::aga()),
+            "nz" | "new zealand" | "newzealand" => Ok(Rules::new_zealand()),
+            "tromp taylor" | "tromptaylor" | "tt" => Ok(Rules::tromp_taylor()),
+            _ => Err(RulesParseError(()))
+        }
+    }
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        f.write_str(self.name().unwrap_or("Custom"))
+    }
+}
+
+#[derive(Debug)]
+pub struct RulesParseError(());
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+    use super::*;
+
+    #[test]
+    fn from_str_known_rulesets() {
+        assert_eq!(Rules::japanese(), Rules::from_str("Japanese").unwrap());
+        assert_eq!(Rules::japanese(), Rules::from_str("jp").unwrap());
+        assert_eq!(Rules::chinese(), Rules::from_str("Chinese").unwrap());
+        assert_eq!(Rules::aga(), Rules::from_str("AGA").unwrap());
+        assert_eq!(Rules::new_zealand(), Rules::from_str("NZ").unwrap());
+        assert_eq!(Rules::new_zealand(), Rules::from_str("New Zealand").unwrap());
+        assert_eq!(Rules::tromp_taylor(), Rules::from_str("Tromp-Taylor").unwrap());
+        assert_eq!(Rules::tromp_taylor(), Rules::from_str("tromp_taylor").unwrap());
+    }
+
+    #[test]
+    fn from_str_invalid() {
+        assert!(Rules::from_str("").is_err());
+        assert!(Rules::from_str("GOE").is_err());
+        assert!(Rules::from_str("whatever").is_err());
+    }
+
+    #[test]
+    fn it_from_str_to_str_eq() {
+        for ru in &["Japanese", "Chinese", "AGA", "NZ", "Tromp-Taylor"] {
+            assert_eq!(format!("{}", Rules::from_str(ru).unwrap()), *ru);
+        }
+        let custom = Rules { suicide : true, .. Rules::japanese() };
+        assert_eq!(None, custom.name());
+        assert_eq!("Custom", format!("{}", custom));
+    }
+
+    #[test]
+    fn it_compensates_handicap() {
+        assert_eq!(0.0, Rules::japanese().compensation(4));
+        assert_eq!(4.0, Rules::chinese().compensation(4));
+        assert_eq!(3.0, Rules::aga().compensation(4));
+        assert_eq!(0.0, Rules::aga().compensation(0));
+        assert_eq!(0.0, Rules::aga().compensation(1));
+        assert_eq!(0.0, Rules::tromp_taylor().compensation(4));
+    }
+
+    #[test]
+    fn rulesets_differ_where_expected() {
+        assert!(!Rules::japanese().suicide);
+        assert!(Rules::new_zealand().suicide);
+        assert!(Rules::tromp_taylor().suicide);
+        assert_eq!(KoRule::Simple, Rules::japanese().ko);
+        assert_eq!(KoRule::SituationalSuperko, Rules::aga().ko);
+        assert_eq!(ScoringMethod::Territory, Rules::japanese().scoring);
+        assert_eq!(ScoringMethod::Area, Rules::chinese().scoring);
+    }
+}
@@ -1,142 +1,333 @@
 use base::board::*;
+use base::bitboard::*;
 use base::game::*;
 use base::game_result::*;
 use base::color::*;
 use base::moves::*;
 use base::coord::*;
-
-use std::collections::HashSet;
+use base::rules::*;
+use base::life::*;
+use base::influence::*;
 
 
 pub fn conservative_floodfill_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+    let territories = floodfill_territories(game.board(), false, game.rules().scoring);
+    (territories.marked_board(game.board()), territories.result_range(game))
+}
 
-    let mut wb = game.board().clone();
+pub fn conservative_floodfill_scorer(game :&Game) -> GameResultRange {
+    floodfill_territories(game.board(), false, game.rules().scoring).result_range(game)
+}
 
-    let mut done = false;
-    let mut curr_find = wb.find_first(Color::Empty);
-    while !done {
-        if let Some(curr) = curr_find {
 
-            let ffr = flood_fill(&wb, curr);
+/// similar to conservative_floodfill_scorer but if there are one or two stones in a group, assumes
+/// they can be killed, unless they are unconditionally alive.
+pub fn optimistic_floodfill_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+    let territories = floodfill_territories(game.board(), true, game.rules().scoring);
+    (territories.marked_board(game.board()), territories.result_range(game))
+}
 
-            let blacks_l = ffr.blacks.len() as isize;
-            let whites_l = ffr.whites.len() as isize;
-            let is_white_territory = blacks_l == 0 && whites_l > 0;
-            let is_black_territory = whites_l == 0 && blacks_l > 0;
+pub fn optimistic_floodfill_scorer(game :&Game) -> GameResultRange {
+    floodfill_territories(game.board(), true, game.rules().scoring).result_range(game)
+}
 
-            if is_black_territory {
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::BlackTerritory));
-                }
-            } else if is_white_territory {
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::WhiteTerritory));
-                }
-            } else {
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::Dame));
-                }
-            }
+/// Tromp-Taylor scoring: every stone on the board plus the empty regions reaching only one
+/// color, and komi. Nothing is guessed dead and no handicap compensation is given, so the result
+/// is exact (range 0); to adjudicate self-play and playouts played to the end.
+pub fn tromp_taylor_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+    let territories = final_territories(game.board(), &BitBoard::new(game.board().size()), false);
+    (territories.marked_board(game.board()), territories.tromp_taylor(game))
+}
 
-            curr_find = wb.find_next(Color::Empty, &curr);
-        } else {
-            done = true;
-        }
-    }
+pub fn tromp_taylor_scorer(game :&Game) -> GameResultRange {
+    final_territories(game.board(), &BitBoard::new(game.board().size()), false).tromp_taylor(game)
+}
 
-    //println!("{}", game.pretty_print_with_board(&wb));
+/// Area (Chinese) scoring once the dead stones are known: the stones left plus the points
+/// surrounded by one color only, komi and, as the rules require, the handicap compensation.
+pub fn area_score(game :&Game, dead :&BitBoard) -> GameResult {
+    final_territories(game.board(), dead, false).score(game, ScoringMethod::Area)
+}
 
-    let result_range = calculate_result_range(&wb, game, 0, 0, 0);
-    (wb, result_range)
+/// Territory (Japanese) scoring once the dead stones are known: the points surrounded by one
+/// color only, but for the eyes in seki, plus the prisoners (dead stones included) and komi.
+pub fn territory_score(game :&Game, dead :&BitBoard) -> GameResult {
+    final_territories(game.board(), dead, true).score(game, ScoringMethod::Territory)
 }
 
-pub fn conservative_floodfill_scorer(game :&Game) -> GameResultRange {
-    conservative_floodfill_scorer_with_board(game).1
+/// area_score or territory_score, as the rules of the game say.
+pub fn final_score(game :&Game, dead :&BitBoard) -> GameResult {
+    match game.rules().scoring {
+        ScoringMethod::Area => area_score(game, dead),
+        ScoringMethod::Territory => territory_score(game, dead)
+    }
 }
 
+/// what the floodfill scorers make of a board, as bitboards so the scorers allocate nothing
+/// unless a marked board is asked for.
+struct Territories {
+    black :BitBoard,
+    white :BitBoard,
+    black_territory :BitBoard,
+    white_territory :BitBoard,
+    dame :BitBoard,
+    extra_black_captured :u16,
+    extra_white_captured :u16,
+    extra_range :u16
+}
 
-/// similar to conservative_floodfill_scorer but if there are one or two stones in a group, assumes
-/// they can be killed.
+/// stones the scorers can't take, worked out only when it matters.
+struct SafeStones {
+    alive :BitBoard,
+    seki  :BitBoard
+}
 
-pub fn optimistic_floodfill_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+impl SafeStones {
+    fn of(board :&Board) -> SafeStones {
+        SafeStones {
+            alive : unconditionally_alive(board, Color::Black) | unconditionally_alive(board, Color::White),
+            seki  : seki(board).iter().fold(BitBoard::new(board.size()), |all, s| all | s.stones)
+        }
+    }
+}
 
-    let mut wb = game.board().clone();
+/// every empty region touching only one color is its territory, the rest dame; if optimistic,
+/// regions touching a few more stones of one color also go to it, taking the others. Stones
+/// alive or in seki are never taken, and with territory scoring the eyes in seki are no one's.
+fn floodfill_territories(board :&Board, optimistic :bool, scoring :ScoringMethod) -> Territories {
+    let size = board.size();
+    let mut black = board.points(Color::Black);
+    let mut white = board.points(Color::White);
+    let mut empty = board.points(Color::Empty);
+    let mut black_territory = board.points(Color::BlackTerritory);
+    let mut white_territory = board.points(Color::WhiteTerritory);
+    let mut dame = board.points(Color::Dame);
 
     let mut extra_black_captured : u16 = 0;
     let mut extra_white_captured : u16 = 0;
+    let mut safe : Option<SafeStones> = None;
+
+    while let Some(curr) = empty.first() {
+        let flooded = BitBoard::from_coords(size, &[curr]).flood(&empty);
+        empty = empty - flooded;
+        let around = flooded.adjacents();
+        let blacks = around & black;
+        let whites = around & white;
+
+        let blacks_l = blacks.count() as isize;
+        let whites_l = whites.count() as isize;
+        let (mut is_black_territory, mut is_white_territory) = if optimistic {
+            (blacks_l > 0 && whites_l < blacks_l && blacks_l - whites_l > 2,
+             whites_l > 0 && blacks_l < whites_l && whites_l - blacks_l > 2)
+        } else {
+            (whites_l == 0 && blacks_l > 0, blacks_l == 0 && whites_l > 0)
+        };
+        if is_black_territory || is_white_territory {
+            let (owners, taken) = if is_black_territory { (blacks, whites) } else { (whites, blacks) };
+            let no_territory_in_seki = scoring == ScoringMethod::Territory;
+            if !taken.is_empty() || no_territory_in_seki {
+                let safe = safe.get_or_insert_with(|| SafeStones::of(board));
+                if !(taken & (safe.alive | safe.seki)).is_empty()
+                    || (no_territory_in_seki && (owners - safe.seki).is_empty()) {
+                    is_black_territory = false;
+                    is_white_territory = false;
+                }
+            }
+        }
+
+        if is_black_territory {
+            extra_white_captured += whites_l as u16;
+            white = white - whites;
+            black_territory = black_territory | flooded | whites;
+        } else if is_white_territory {
+            extra_black_captured += blacks_l as u16;
+            black = black - blacks;
+            white_territory = white_territory | flooded | blacks;
+        } else {
+            dame = dame | flooded;
+        }
+    }
 
-    let mut done = false;
-    let mut curr_find = wb.find_first(Color::Empty);
-    while !done {
-        if let Some(curr) = curr_find {
+    Territories {
+        black,
+        white,
+        black_territory,
+        white_territory,
+        dame,
+        extra_black_captured,
+        extra_white_captured,
+        extra_range : (extra_black_captured + extra_white_captured) * 3
+    }
+}
 
-            let ffr = flood_fill(&wb, curr);
+/// Scores a finished game with the dead chains agreed by the players (GTP's final_status_list,
+/// or the stones in the other color's SGF TB/TW marks): each coord kills its whole chain, empty
+/// ones are ignored. The board comes marked with territory and dame, as the rules count them.
+pub fn dead_chains_scorer_with_board(game :&Game, dead_chains :&[Coord]) -> (Board, GameResult) {
+    let board = game.board();
+    let mut dead = BitBoard::new(board.size());
+    for chain in dead_chains.iter().filter_map(|c| board.chain_at(c)) {
+        dead = dead | chain.points();
+    }
+    let scoring = game.rules().scoring;
+    let territories = final_territories(board, &dead, scoring == ScoringMethod::Territory);
+    (territories.marked_board(board), territories.score(game, scoring))
+}
 
-            let blacks_l = ffr.blacks.len() as isize;
-            let whites_l = ffr.whites.len() as isize;
+/// the stones standing in the territory marked for the other color, i.e. the dead ones in a
+/// record's final TB and TW.
+pub fn dead_stones_in_territory(board :&Board, black_territory :&[Coord], white_territory :&[Coord]) -> Vec<Coord> {
+    let in_black = black_territory.iter().filter(|c| board.get(c) == Color::White);
+    let in_white = white_territory.iter().filter(|c| board.get(c) == Color::Black);
+    in_black.chain(in_white).cloned().collect()
+}
 
-            let is_white_territory = whites_l > 0 && blacks_l < whites_l && whites_l - blacks_l > 2;
-            let is_black_territory = blacks_l > 0 && whites_l < blacks_l && blacks_l - whites_l > 2;
+/// Bouzy's 5/21 influence as territory: empty points held by a color are its territory, the rest
+/// dame, and stones are never taken. Unlike the floodfill scorers it tells something in the
+/// middle game, where most regions touch both colors; the marked board is a handy hint.
+pub fn influence_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+    let board = game.board();
+    let influence = Influence::bouzy_5_21(board);
+    let mut marked = board.clone();
+    for c in &board.points(Color::Empty) {
+        let mark = match influence.owner(&c) {
+            Color::Black => Color::BlackTerritory,
+            Color::White => Color::WhiteTerritory,
+            _            => Color::Dame
+        };
+        marked.set_move(Move::Stone(c, mark));
+    }
+    let result = marked_board_result_range(game, &marked, 0, 0);
+    (marked, result)
+}
 
-            if is_black_territory {
-                for coord in ffr.whites {
-                    extra_white_captured = extra_white_captured + 1;
-                    wb.set_move(Move::Stone(coord, Color::BlackTerritory));
-                }
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::BlackTerritory));
-                }
-            } else if is_white_territory {
-                for coord in ffr.blacks {
-                    extra_black_captured = extra_black_captured + 1;
-                    wb.set_move(Move::Stone(coord, Color::WhiteTerritory));
-                }
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::WhiteTerritory));
-                }
-            } else {
-                for coord in ffr.flooded {
-                    wb.set_move(Move::Stone(coord, Color::Dame));
-                }
-            }
+pub fn influence_scorer(game :&Game) -> GameResultRange {
+    influence_scorer_with_board(game).1
+}
 
-            curr_find = wb.find_next(Color::Empty, &curr);
+/// the territories of a finished game: dead stones are taken off, and every region of empty
+/// points and dead stones belongs to the color around it, if there is only one.
+fn final_territories(board :&Board, dead :&BitBoard, no_territory_in_seki :bool) -> Territories {
+    let size = board.size();
+    let dead_black = *dead & board.points(Color::Black);
+    let dead_white = *dead & board.points(Color::White);
+    let black = board.points(Color::Black) - dead_black;
+    let white = board.points(Color::White) - dead_white;
+    let mut black_territory = board.points(Color::BlackTerritory);
+    let mut white_territory = board.points(Color::WhiteTerritory);
+    let mut dame = board.points(Color::Dame);
+
+    let seki_stones = if no_territory_in_seki {
+        let mut alive = board.clone();
+        for c in &(dead_black | dead_white) {
+            alive.set_move(Move::Stone(c, Color::Empty));
+        }
+        seki(&alive).iter().fold(BitBoard::new(size), |stones, s| stones | s.stones)
+    } else {
+        BitBoard::new(size)
+    };
+
+    let mut rest = board.points(Color::Empty) | dead_black | dead_white;
+    while let Some(curr) = rest.first() {
+        let region = BitBoard::from_coords(size, &[curr]).flood(&rest);
+        rest = rest - region;
+        let around = region.adjacents();
+        let blacks = around & black;
+        let whites = around & white;
+        if whites.is_empty() && !(blacks - seki_stones).is_empty() {
+            black_territory = black_territory | region;
+        } else if blacks.is_empty() && !(whites - seki_stones).is_empty() {
+            white_territory = white_territory | region;
         } else {
-            done = true;
+            dame = dame | region;
         }
     }
 
-    //println!("{}", game.pretty_print_with_board(&wb));
-
-    let extra_range = (extra_black_captured + extra_white_captured) * 3;
-    let result_range = calculate_result_range(&wb, game, extra_black_captured, extra_white_captured, extra_range);
-    (wb, result_range)
+    Territories {
+        black,
+        white,
+        black_territory,
+        white_territory,
+        dame,
+        extra_black_captured : dead_black.count() as u16,
+        extra_white_captured : dead_white.count() as u16,
+        extra_range : 0
+    }
 }
 
-pub fn optimistic_floodfill_scorer(game :&Game) -> GameResultRange {
-    optimistic_floodfill_scorer_with_board(game).1
-}
+impl Territories {
 
-fn calculate_result_range(board :&Board, game :&Game, extra_black_captured :u16, extra_white_captured :u16, extra_range :u16) -> GameResultRange {
+    fn marked_board(&self, board :&Board) -> Board {
+        let mut wb = board.clone();
+        for &(points, color) in &[(&self.black_territory, Color::BlackTerritory),
+                                  (&self.white_territory, Color::WhiteTerritory),
+                                  (&self.dame, Color::Dame)] {
+            for coord in points {
+                wb.set_move(Move::Stone(coord, color));
+            }
+        }
+        wb
+    }
 
-    let counted_colors = count_colors(board);
+    fn counted_colors(&self) -> CountColorsResult {
+        CountColorsResult {
+            empty: 0,
+            black: self.black.count() as u16,
+            white: self.white.count() as u16,
+            dame: self.dame.count() as u16,
+            black_territory: self.black_territory.count() as u16,
+            white_territory: self.white_territory.count() as u16
+        }
+    }
 
-    // calculate score, positive is white, negative is black.
-    let count : f32 = game.komi()
-                    + game.captured_count(Color::Black) as f32 // black captures is white points
-                    - game.captured_count(Color::White) as f32
-                    + extra_black_captured as f32
-                    - extra_white_captured as f32
-                    + counted_colors.white as f32
-                    - counted_colors.black as f32
-                    + counted_colors.white_territory as f32
-                    - counted_colors.black_territory as f32;
+    fn result_range(&self, game :&Game) -> GameResultRange {
+        calculate_result_range(&self.counted_colors(), game, self.extra_black_captured, self.extra_white_captured, self.extra_range)
+    }
+
+    fn tromp_taylor(&self, game :&Game) -> GameResultRange {
+        let count = game.komi()
+                  + (self.white.count() + self.white_territory.count()) as f32
+                  - (self.black.count() + self.black_territory.count()) as f32;
+        GameResultRange::new(GameResult::from_score(count), 0)
+    }
+
+    fn score(&self, game :&Game, scoring :ScoringMethod) -> GameResult {
+        GameResult::from_score(calculate_score(&self.counted_colors(), game, scoring, self.extra_black_captured, self.extra_white_captured))
+    }
+}
 
-    let winning_color = if count<0.0 { Color::Black } else { Color::White };
-    let winning_score = count.abs();
+fn calculate_result_range(counted_colors :&CountColorsResult, game :&Game, extra_black_captured :u16, extra_white_captured :u16, extra_range :u16) -> GameResultRange {
+    let count = calculate_score(counted_colors, game, game.rules().scoring, extra_black_captured, extra_white_captured);
     let range = counted_colors.dame + counted_colors.empty + extra_range;
+    GameResultRange::new(GameResult::from_score(count), range)
+}
 
-    GameResultRange::new(GameResult::Score(winning_color, winning_score), range)
+/// the result of a board some other estimator marked with territory and dame, the dead stones
+/// it took off counted as prisoners; the range are the dame and the points left unmarked.
+pub fn marked_board_result_range(game :&Game, marked :&Board, dead_black :u16, dead_white :u16) -> GameResultRange {
+    calculate_result_range(&count_colors(marked), game, dead_black, dead_white, 0)
+}
+
+/// the points of white minus those of black: stones and territory for area scoring, territory
+/// and prisoners for territory scoring.
+fn calculate_score(counted_colors :&CountColorsResult, game :&Game, scoring :ScoringMethod, extra_black_captured :u16, extra_white_captured :u16) -> f32 {
+    let mut count : f32 = game.komi()
+                    + counted_colors.white_territory as f32
+                    - counted_colors.black_territory as f32;
+    match scoring {
+        ScoringMethod::Area => {
+            count += counted_colors.white as f32
+                   - counted_colors.black as f32
+                   + game.rules().compensation(game.handicap());
+        },
+        ScoringMethod::Territory => {
+            count += game.captured_count(Color::Black) as f32 // black captures is white points
+                   - game.captured_count(Color::White) as f32
+                   + extra_black_captured as f32
+                   - extra_white_captured as f32;
+        }
+    }
+    count
 }
 
 
@@ -146,38 +337,14 @@ pub struct FloodFillResult {
     pub blacks: Vec<Coord>
 }
 
+/// the empty region at coord and the stones around it.
 pub fn flood_fill(board : &Board, coord : Coord) -> FloodFillResult {
-    let mut flooded : HashSet<Coord> = HashSet::new();
-    let mut whites : HashSet<Coord> = HashSet::new();
-    let mut blacks : HashSet<Coord> = HashSet::new();
-
-    let mut stack = vec!();
-    if board.get(&coord) == Color::Empty {
-        flooded.insert(coord);
-        stack.push(coord);
-    }
-    while !stack.is_empty() {
-        let curr = stack.pop().unwrap();
-        let adjs = curr.adjacents(board.size());
-        for adj in adjs {
-            let adj_color = board.get(&adj);
-            if adj_color == Color::Empty {
-                if flooded.insert(adj) {
-                    stack.push(adj);
-                }
-            } else if adj_color == Color::White {
-                whites.insert(adj);
-            } else if adj_color == Color::Black {
-                blacks.insert(adj);
-            }
-        }
-    }
-
-
+    let flooded = BitBoard::from_coords(board.size(), &[coord]).flood(&board.points(Color::Empty));
+    let around = flooded.adjacents();
     FloodFillResult {
-        flooded: flooded.into_iter().collect(),
-        whites: whites.into_iter().collect(),
-        blacks: blacks.into_iter().collect()
+        flooded: flooded.iter().collect(),
+        whites: (around & board.points(Color::White)).iter().collect(),
+        blacks: (around & board.points(Color::Black)).iter().collect()
     }
 }
 
@@ -191,31 +358,268 @@ pub struct CountColorsResult {
 }
 
 pub fn count_colors(board :&Board) -> CountColorsResult {
-    // the following is so we don't need to get-set in a hashmap for eat entry
-    let mut empty : u16 = 0;
-    let mut black : u16 = 0;
-    let mut white : u16 = 0;
-    let mut black_territory : u16 = 0;
-    let mut white_territory : u16 = 0;
-    let mut dame : u16 = 0;
+    let count = |color| board.points(color).count() as u16;
+    CountColorsResult {
+        empty: count(Color::Empty),
+        black: count(Color::Black),
+        white: count(Color::White),
+        dame: count(Color::Dame),
+        black_territory: count(Color::BlackTerritory),
+        white_territory: count(Color::WhiteTerritory)
+    }
+}
+
 
-    for color in board.data() {
-        match color {
-            &Color::Empty => empty = empty + 1,
-            &Color::Black => black = black + 1,
-            &Color::White => white = white + 1,
-            &Color::BlackTerritory => black_territory = black_territory + 1,
-            &Color::WhiteTerritory => white_territory = white_territory + 1,
-            &Color::Dame => dame = dame + 1,
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// a 9x9 with a black wall on C and a white one on G, plus the given extra stones.
+    fn walled_game(komi :f32, extra :&[Move]) -> Game {
+        let mut g = Game::new(9, komi, 0);
+        let mut stones = vec!();
+        for row in 0..9 {
+            stones.push(Move::Stone(Coord::new(row, 2), Color::Black));
+            stones.push(Move::Stone(Coord::new(row, 6), Color::White));
         }
+        stones.extend_from_slice(extra);
+        g.setup_stones(&stones).unwrap();
+        g
     }
 
-    CountColorsResult {
-        empty: empty,
-        black: black,
-        white: white,
-        dame: dame,
-        black_territory: black_territory,
-        white_territory: white_territory
+    fn get(board :&Board, coord :&str) -> Color {
+        board.get(&Coord::from_str(coord).unwrap())
+    }
+
+    #[test]
+    fn it_floods_an_empty_region() {
+        let g = walled_game(0.5, &[]);
+        let ffr = flood_fill(g.board(), Coord::from_str("E5").unwrap());
+        assert_eq!(27, ffr.flooded.len());
+        assert_eq!(9, ffr.blacks.len());
+        assert_eq!(9, ffr.whites.len());
+        assert!(flood_fill(g.board(), Coord::from_str("C5").unwrap()).flooded.is_empty());
+    }
+
+    #[test]
+    fn conservative_scorer_marks_territory_and_dame() {
+        let g = walled_game(0.5, &[]);
+        let (board, result) = conservative_floodfill_scorer_with_board(&g);
+        assert_eq!(Color::BlackTerritory, get(&board, "A1"));
+        assert_eq!(Color::WhiteTerritory, get(&board, "J9"));
+        assert_eq!(Color::Dame, get(&board, "E5"));
+        assert_eq!(Color::Black, get(&board, "C5"));
+        // 27 points each, komi decides
+        assert_eq!(GameResultRange::new(GameResult::Score(Color::White, 0.5), 27), result);
+        assert_eq!(result, conservative_floodfill_scorer(&g));
+    }
+
+    #[test]
+    fn optimistic_scorer_takes_lonely_stones() {
+        let g = walled_game(0.5, &[Move::Stone(Coord::from_str("A5").unwrap(), Color::White)]);
+        let (board, result) = optimistic_floodfill_scorer_with_board(&g);
+        assert_eq!(Color::BlackTerritory, get(&board, "A5"));
+        assert_eq!(Color::BlackTerritory, get(&board, "A1"));
+        assert_eq!(GameResultRange::new(GameResult::Score(Color::White, 0.5), 30), result);
+        assert_eq!(result, optimistic_floodfill_scorer(&g));
+
+        let (board, result) = conservative_floodfill_scorer_with_board(&g);
+        assert_eq!(Color::White, get(&board, "A5"));
+        assert_eq!(Color::Dame, get(&board, "A1"));
+        assert_eq!(44, result.range);
+    }
+
+    #[test]
+    fn optimistic_scorer_leaves_unconditionally_alive_stones() {
+        // a small white group with two eyes at A1 and C1, next to the black wall on G
+        let mut g = Game::new(9, 0.5, 0);
+        let mut stones = vec!();
+        for c in &["B1", "A2", "B2", "C2", "D2", "D1"] {
+            stones.push(Move::Stone(Coord::from_str(c).unwrap(), Color::White));
+        }
+        for row in 0..9 {
+            stones.push(Move::Stone(Coord::new(row, 6), Color::Black));
+        }
+        g.setup_stones(&stones).unwrap();
+        let (board, _) = optimistic_floodfill_scorer_with_board(&g);
+        assert_eq!(Color::White, get(&board, "A2"));
+        assert_eq!(Color::WhiteTerritory, get(&board, "C1"));
+        assert_eq!(Color::Dame, get(&board, "E5"));
+    }
+
+    #[test]
+    fn it_counts_colors() {
+        let (board, _) = conservative_floodfill_scorer_with_board(&walled_game(0.5, &[]));
+        let counted = count_colors(&board);
+        assert_eq!((0, 9, 9, 27, 18, 18), (counted.empty, counted.black, counted.white, counted.dame,
+                                           counted.black_territory, counted.white_territory));
+    }
+
+    fn dead(coords :&[&str]) -> BitBoard {
+        BitBoard::from_coords(9, &coords.iter().map(|c| Coord::from_str(c).unwrap()).collect::<Vec<Coord>>())
+    }
+
+    #[test]
+    fn area_and_territory_scores_can_be_jigo() {
+        let g = walled_game(0.0, &[]);
+        assert_eq!(GameResult::Draw, area_score(&g, &dead(&[])));
+        assert_eq!(GameResult::Draw, territory_score(&g, &dead(&[])));
+        assert_eq!(GameResult::Draw, conservative_floodfill_scorer(&g).result);
+    }
+
+    #[test]
+    fn prisoners_count_only_for_territory_scoring() {
+        let mut g = walled_game(0.0, &[]);
+        for &(c, color) in &[("B1", Color::Black), ("A1", Color::White), ("A2", Color::Black)] {
+            g.play(Move::Stone(Coord::from_str(c).unwrap(), color));
+        }
+        assert_eq!(1, g.captured_count(Color::White));
+        // black filled two points of its own to take one
+        assert_eq!(GameResult::Draw, area_score(&g, &dead(&[])));
+        assert_eq!(GameResult::Score(Color::White, 1.0), territory_score(&g, &dead(&[])));
+    }
+
+    #[test]
+    fn dead_stones_are_taken_off() {
+        let mut g = walled_game(0.0, &[Move::Stone(Coord::from_str("A5").unwrap(), Color::White)]);
+        assert_eq!(GameResult::Draw, area_score(&g, &dead(&["A5"])));
+        assert_eq!(GameResult::Score(Color::Black, 1.0), territory_score(&g, &dead(&["A5"])));
+        // alive, it makes the A and B columns dame
+        assert_eq!(GameResult::Score(Color::White, 19.0), area_score(&g, &dead(&[])));
+        g.set_rules(Rules::japanese());
+        assert_eq!(territory_score(&g, &dead(&["A5"])), final_score(&g, &dead(&["A5"])));
+    }
+
+    #[test]
+    fn handicap_is_compensated_as_the_rules_say() {
+        let mut g = Game::new(9, 0.0, 2);
+        let mut stones : Vec<Move> = Coord::all_possibles(9).into_iter().map(|c| Move::Stone(c, Color::Empty)).collect();
+        for row in 0..9 {
+            stones.push(Move::Stone(Coord::new(row, 2), Color::Black));
+            stones.push(Move::Stone(Coord::new(row, 6), Color::White));
+        }
+        g.setup_stones(&stones).unwrap();
+        assert_eq!(GameResult::Score(Color::White, 2.0), area_score(&g, &dead(&[])));
+        assert_eq!(GameResult::Draw, territory_score(&g, &dead(&[])));
+        g.set_rules(Rules::aga());
+        assert_eq!(GameResult::Score(Color::White, 1.0), area_score(&g, &dead(&[])));
+    }
+
+    #[test]
+    fn tromp_taylor_counts_every_stone_as_alive() {
+        let g = walled_game(0.5, &[Move::Stone(Coord::from_str("A5").unwrap(), Color::White)]);
+        let (board, result) = tromp_taylor_scorer_with_board(&g);
+        // the A and B columns reach both colors: black has its wall, white 28 points and komi
+        assert_eq!(GameResultRange::new(GameResult::Score(Color::White, 19.5), 0), result);
+        assert_eq!(Color::Dame, get(&board, "A1"));
+        assert_eq!(Color::White, get(&board, "A5"));
+        assert_eq!(Color::WhiteTerritory, get(&board, "J9"));
+        assert_eq!(result, tromp_taylor_scorer(&g));
+    }
+
+    #[test]
+    fn tromp_taylor_ignores_captures_and_handicap() {
+        let mut g = Game::new(9, 0.0, 2);
+        let mut stones : Vec<Move> = Coord::all_possibles(9).into_iter().map(|c| Move::Stone(c, Color::Empty)).collect();
+        for row in 0..9 {
+            stones.push(Move::Stone(Coord::new(row, 2), Color::Black));
+            stones.push(Move::Stone(Coord::new(row, 6), Color::White));
+        }
+        g.setup_stones(&stones).unwrap();
+        for &(c, color) in &[("H1", Color::White), ("J1", Color::Black), ("J2", Color::White)] {
+            g.play(Move::Stone(Coord::from_str(c).unwrap(), color));
+        }
+        assert_eq!(1, g.captured_count(Color::Black));
+        assert_eq!(GameResultRange::new(GameResult::Draw, 0), tromp_taylor_scorer(&g));
+    }
+
+    #[test]
+    fn it_scores_with_the_dead_chains_given() {
+        // a white chain of two inside black's area
+        let mut g = walled_game(0.5, &[Move::Stone(Coord::from_str("A5").unwrap(), Color::White),
+                                       Move::Stone(Coord::from_str("A6").unwrap(), Color::White)]);
+        g.set_rules(Rules::japanese());
+        let (board, result) = dead_chains_scorer_with_board(&g, &[Coord::from_str("A5").unwrap(), Coord::from_str("E5").unwrap()]);
+        assert_eq!(Color::BlackTerritory, get(&board, "A6"));
+        assert_eq!(Color::BlackTerritory, get(&board, "A1"));
+        assert_eq!(Color::WhiteTerritory, get(&board, "J1"));
+        assert_eq!(Color::Dame, get(&board, "E5"));
+        assert_eq!(Color::Black, get(&board, "C5"));
+        // 18 points and 2 prisoners against 18 and komi
+        assert_eq!(GameResult::Score(Color::Black, 1.5), result);
+
+        let (board, result) = dead_chains_scorer_with_board(&g, &[]);
+        assert_eq!(Color::White, get(&board, "A6"));
+        assert_eq!(Color::Dame, get(&board, "A1"));
+        assert_eq!(GameResult::Score(Color::White, 18.5), result);
+    }
+
+    #[test]
+    fn it_finds_dead_stones_in_territory_marks() {
+        let g = walled_game(0.5, &[Move::Stone(Coord::from_str("A5").unwrap(), Color::White),
+                                   Move::Stone(Coord::from_str("J5").unwrap(), Color::White)]);
+        let marks = |cs :&[&str]| cs.iter().map(|c| Coord::from_str(c).unwrap()).collect::<Vec<Coord>>();
+        let dead = dead_stones_in_territory(g.board(), &marks(&["A4", "A5", "A6"]), &marks(&["H5", "J5"]));
+        assert_eq!(marks(&["A5"]), dead);
+    }
+
+    #[test]
+    fn influence_scorer_estimates_open_games() {
+        let mut g = Game::new(9, 0.5, 0);
+        let mut stones = vec!();
+        for row in 0..4 {
+            stones.push(Move::Stone(Coord::new(row, 2), Color::Black));
+            stones.push(Move::Stone(Coord::new(row, 6), Color::White));
+        }
+        g.setup_stones(&stones).unwrap();
+        // one region touching both colors, all dame for the floodfill scorers
+        assert_eq!(73, conservative_floodfill_scorer(&g).range);
+        let (board, result) = influence_scorer_with_board(&g);
+        assert_eq!(Color::BlackTerritory, get(&board, "A1"));
+        assert_eq!(Color::WhiteTerritory, get(&board, "J4"));
+        assert_eq!(Color::Dame, get(&board, "E1"));
+        assert_eq!(Color::Black, get(&board, "C1"));
+        assert_eq!(GameResultRange::new(GameResult::Score(Color::White, 0.5), 57), result);
+        assert_eq!(result, influence_scorer(&g));
+    }
+
+    //
+    // benchs
+    //
+    use test::Bencher;
+
+    fn random_game(board_size :usize, moves :usize) -> Game {
+        let mut g = Game::new(board_size, 5.5, 0);
+        let mut tries = 0;
+        while g.move_count() < moves && tries < 100 * moves {
+            let turn = g.next_turn();
+            g.play(Move::Stone(Coord::random(board_size), turn));
+            tries += 1;
+        }
+        g
+    }
+
+    #[bench]
+    fn conservative_floodfill_19x19(b: &mut Bencher) {
+        let g = random_game(19, 250);
+        b.iter(|| conservative_floodfill_scorer(&g));
+    }
+
+    #[bench]
+    fn optimistic_floodfill_19x19(b: &mut Bencher) {
+        let g = random_game(19, 250);
+        b.iter(|| optimistic_floodfill_scorer(&g));
+    }
+
+    #[bench]
+    fn influence_19x19(b: &mut Bencher) {
+        let g = random_game(19, 250);
+        b.iter(|| influence_scorer(&g));
     }
 }
@@ -7,6 +7,7 @@ use base::color::*;
 use base::coord::*;
 use base::moves::*;
 use base::rank::*;
+use base::rules::*;
 
 
     #[derive(Debug)]
@@ -21,13 +22,12 @@ use base::rank::*;
             return Err(SgfParsingError::Cause(String::from("Empty-ish file")));
         }
 
-        let mut gt = GameTree::new();
-        match recursive_greedy_parser(&mut gt, &chrs, start_ii) {
-            Ok(ii) => {
+        match parse_game_tree(&chrs, start_ii) {
+            Ok((ii, gt)) => {
                 if ii == chrs.len()  {
                     Ok(gt)
                 } else {
-                    let errmsg = format!("couldn't reach EOF while parsing, ended up in {} out of {}.", ii, chrs.len());
+                    let errmsg = format!("couldn't reach EOF while parsing, ended up in {} out of {} (use parse_collection for many games).", ii, chrs.len());
                     Err(SgfParsingError::Cause(errmsg))
                 }
             }
@@ -36,57 +36,105 @@ use base::rank::*;
 
     }
 
-    fn recursive_greedy_parser(gt : &mut GameTree, chrs :&Vec<char>, i : usize) -> Result<usize,SgfParsingError> {
+    /// Parses all the games in a file, i.e. "(;...)(;...)".
+    pub fn parse_collection(s : String) -> Result<Vec<GameTree>, SgfParsingError> {
+        let chrs : Vec<char> = s.chars().collect();
+        let mut ii = skip_cr_lf_sp(&chrs, 0);
+        if ii == chrs.len() {
+            return Err(SgfParsingError::Cause(String::from("Empty-ish file")));
+        }
+
+        let mut gts : Vec<GameTree> = vec!();
+        while ii < chrs.len() {
+            match parse_game_tree(&chrs, ii) {
+                Ok((p, gt)) => { ii = p; gts.push(gt) },
+                Err(c)      => return Err(SgfParsingError::Cause(format!("game #{}: {:?}", gts.len() + 1, c)))
+            }
+        }
+        Ok(gts)
+    }
+
+    fn parse_game_tree(chrs :&Vec<char>, i :usize) -> Result<(usize, GameTree), SgfParsingError> {
+        let mut gt = GameTree::new();
+        gt.set_board_size(19); // SGF default when there is no SZ
+        let mut line : Vec<GameNode> = vec!();
+        let ii = parse_sub_tree(&mut gt, chrs, i, &mut line)?;
+        // the first node is the root, which might have variants if moves start in a sub-tree
+        gt.set_root(line.remove(0));
+        for gn in line {
+            gt.push(gn);
+        }
+        Ok((ii, gt))
+    }
+
+    // parses "( sequence sub-trees* )" appending the sequence into the given line, the first sub-tree
+    // continues the line and the following ones become variants of the last node of the sequence.
+    fn parse_sub_tree(gt : &mut GameTree, chrs :&Vec<char>, i : usize, line :&mut Vec<GameNode>) -> Result<usize,SgfParsingError> {
 
-        let mut prev_cmd : String = String::new();
         let mut ii = skip_cr_lf_sp(chrs, i);
-        if let Some(&ch) = chrs.get(ii) {
-            if ch != '(' {
-                return Err(SgfParsingError::Cause(String::from("it is not starting with (")))
+        if chrs.get(ii) != Some(&'(') {
+            return Err(SgfParsingError::Cause(String::from("it is not starting with (")))
+        }
+        ii = skip_cr_lf_sp(chrs, ii + 1);
+
+        // sequence
+        let line_start = line.len();
+        while chrs.get(ii) == Some(&';') {
+            match parse_node(gt, chrs, ii + 1) {
+                Ok((p, gn)) => { ii = p; line.push(gn) },
+                Err(c)      => return Err(c)
+            }
+        }
+        if line.len() == line_start {
+            return Err(SgfParsingError::Cause(format!("a game tree needs at least one node, at {}", ii)))
+        }
+        let branch = line.len() - 1;
+
+        // sub-trees
+        let mut first = true;
+        while chrs.get(ii) == Some(&'(') {
+            if first {
+                ii = parse_sub_tree(gt, chrs, ii, line)?;
+                first = false;
             } else {
-                ii = ii + 1
+                let mut variant : Vec<GameNode> = vec!();
+                ii = parse_sub_tree(gt, chrs, ii, &mut variant)?;
+                line[branch].push_variant(variant);
             }
         }
 
-        ii = skip_cr_lf_sp(chrs, ii);
+        if chrs.get(ii) != Some(&')') {
+            return Err(SgfParsingError::Cause(format!("it can't find the closing ) at {}", ii)))
+        }
+        Ok(skip_cr_lf_sp(chrs, ii + 1))
+    }
 
-        loop {
-            if let Some(&ch) = chrs.get(ii) {
-                if ch == ')' {
-                    ii = skip_cr_lf_sp(chrs, ii+1);
-                    break
-                }
-                if ch == ';' {
-                    ii = ii + 1;
-                    ii = skip_cr_lf_sp(chrs, ii);
-                }
-                if ch == ')' {
-                    // we are done
-                    return Ok(ii+1)
-                }
-            } else {
+    fn parse_node(gt : &mut GameTree, chrs :&Vec<char>, i : usize) -> Result<(usize, GameNode), SgfParsingError> {
+        let mut gn = GameNode::new_empty();
+        let mut prev_cmd : String = String::new();
+        let mut ii = skip_cr_lf_sp(chrs, i);
+        while let Some(&ch) = chrs.get(ii) {
+            if ch == ';' || ch == '(' || ch == ')' {
                 break
             }
-
-            match process_command_if_avail(gt, chrs, ii, prev_cmd) {
+            match process_command_if_avail(gt, &mut gn, chrs, ii, prev_cmd) {
                 Ok((p, cmd)) => {ii = p; prev_cmd = cmd},
                 Err(c)     => return Err(c)
             }
-
             ii = skip_cr_lf_sp(chrs, ii);
         }
-        Ok(ii)
+        Ok((ii, gn))
     }
 
-    fn process_command_if_avail(gt : &mut GameTree, chrs :&Vec<char>, i :usize, prev_cmd : String) -> Result<(usize, String), SgfParsingError> {
+    fn process_command_if_avail(gt : &mut GameTree, gn : &mut GameNode, chrs :&Vec<char>, i :usize, prev_cmd : String) -> Result<(usize, String), SgfParsingError> {
 
         let next_open_bracket = scan_with_limit(chrs,'[', i, chrs.len());
-        let next_closing_bracket = scan_with_limit(chrs, ']', next_open_bracket, chrs.len());
+        let next_closing_bracket = scan_closing_bracket(chrs, next_open_bracket);
         if next_open_bracket >= chrs.len() || next_closing_bracket >= chrs.len() {
             return Err(SgfParsingError::Cause(String::from("It can't find opening and closing brackets.")))
         }
 
-        let cmd = if i==next_open_bracket { prev_cmd } else { up_string(chrs, i, next_open_bracket) };
+        let cmd = if i==next_open_bracket { prev_cmd } else { up_string(chrs, i, next_open_bracket).trim().to_string() };
         let params = sub_string(chrs, next_open_bracket + 1, next_closing_bracket);
 
         //println!("cmd={} and params={}", cmd, params);
@@ -118,10 +166,6 @@ use base::rank::*;
                     Ok(rank) => gt.set_black_rank(rank),
                     Err(_)   => return Err(SgfParsingError::Cause(format!("BR (rank) can't be parsed: {}", &params)))
             }
-        } else if cmd == "DT" { // game date
-            // skip
-        } else if cmd == "PC" { // place where game was played
-            // skip
         } else if cmd == "KM" {
             match f32::from_str(&params) {
                 Ok(komi) => gt.set_komi(komi),
@@ -132,36 +176,39 @@ use base::rank::*;
                 Ok(ha) => gt.set_handicap(ha),
                 Err(_) => return Err(SgfParsingError::Cause(format!("HA (handicap) can't be parsed into an int, value: {}", &params)))
             }
+        } else if cmd == "RU" { // rules, unknown ones are kept as they come
+            match Rules::from_str(&params) {
+                Ok(rules) => gt.set_rules(Some(rules)),
+                Err(_)    => gn.add_property(&cmd, params)
+            }
         } else if cmd == "RE" {
             match GameResult::from_str(&params) {
                 Ok(result) => gt.set_result(result),
                 Err(_)     => return Err(SgfParsingError::Cause(format!("RE (Result) can't be parsed, value: {}", &params)))
             }
-        } else if cmd == "RU" { // skip rules
-        } else if cmd == "CA" { // skip charset
-        } else if cmd == "ST" { // skip how variations should be shown
-        } else if cmd == "AP" { // skip 'Application name'
-        } else if cmd == "TM" { // skip 'Time Limit'
-        } else if cmd == "OT" { // skip 'Overtime method'
-
-        } else if cmd == "AB" { // adds black stones *not a move*
-            // !!! this assumes it will be handled by 'handicap' setting in the game
-            // this parser is not intended to be used as a generic parser for demostrations, etc.
-            // gametree does not handle this sort of events, might consider fixed or improvement
-            // in the future //FIXME -maybe-
-        } else if cmd == "AW" { // adds white stones *not a move*
-            // ^^ READ "AB" comment
         } else if cmd == "W" || cmd == "B" { // White moves, Black moves
             match get_move(&cmd, &params, gt.board_size()) {
-                Ok(m)  => gt.push(GameNode::new_simple(m)),
+                Ok(m)  => gn.set_move(m),
                 Err(_) => return Err(SgfParsingError::Cause(String::from("W/R (a move) can't be parsed")))
             }
-        } else if cmd == "XX" {
-        } else if cmd == "XX" {
-
-        } else {
-            println!("I dont know how to deal with: {}[{}]", cmd, params);
-            return Err(SgfParsingError::Cause(String::from(format!("Unknown command: {}[{}]", cmd, params))))
+        } else if cmd == "AB" || cmd == "AW" || cmd == "AE" { // setup stones *not a move*
+            let color = if cmd == "AB" { Color::Black } else if cmd == "AW" { Color::White } else { Color::Empty };
+            match get_points(&params, gt.board_size()) {
+                Ok(coords) => for coord in coords { gn.add_setup(Move::Stone(coord, color)) },
+                Err(_)     => return Err(SgfParsingError::Cause(format!("{} (setup) can't be parsed, value: {}", cmd, params)))
+            }
+        } else if cmd == "PL" { // player to move
+            match params.to_uppercase().as_ref() {
+                "B" | "1" => gn.set_player(Color::Black),
+                "W" | "2" => gn.set_player(Color::White),
+                _         => return Err(SgfParsingError::Cause(format!("PL (player) can't be parsed, value: {}", params)))
+            }
+        } else if cmd == "C" { // comment
+            gn.set_comment(params);
+        } else if cmd.is_empty() || !cmd.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(SgfParsingError::Cause(format!("Invalid property identifier: {}[{}]", cmd, params)))
+        } else { // anything else is kept as it is, i.e. DT, PC, EV, GN, LB, TR, TB, TW...
+            gn.add_property(&cmd, params);
         }
 
         Ok((next_closing_bracket + 1, cmd))
@@ -186,6 +233,39 @@ use base::rank::*;
         }
     }
 
+    // a point or a compressed rectangle of points, i.e. "aa:cc"
+    fn get_points(params :&str, board_size :usize) -> Result<Vec<Coord>,()> {
+        let mut split = params.split(':');
+        let a = Coord::from_sgf(split.next().unwrap_or(""), board_size as u8)?;
+        let b = match split.next() {
+            Some(p) => Coord::from_sgf(p, board_size as u8)?,
+            None    => a
+        };
+        if split.next().is_some() {
+            return Err(())
+        }
+        let mut coords = vec!();
+        for row in a.row.min(b.row)..(a.row.max(b.row) + 1) {
+            for col in a.col.min(b.col)..(a.col.max(b.col) + 1) {
+                coords.push(Coord::new(row, col));
+            }
+        }
+        Ok(coords)
+    }
+
+    /// the points listed in a node property, i.e. the territory marked with TB and TW at the end
+    /// of a game; none if the node doesn't have it.
+    pub fn property_points(gn :&GameNode, id :&str, board_size :usize) -> Result<Vec<Coord>, SgfParsingError> {
+        let mut coords = vec!();
+        for params in gn.property(id).into_iter().flatten() {
+            match get_points(params, board_size) {
+                Ok(points) => coords.extend(points),
+                Err(_)     => return Err(SgfParsingError::Cause(format!("{} (points) can't be parsed, value: {}", id, params)))
+            }
+        }
+        Ok(coords)
+    }
+
     fn scan_with_limit(chrs :&Vec<char>, scan_ch :char, i :usize, max :usize) -> usize {
         let mut ii = i;
         while ii < max {
@@ -199,6 +279,20 @@ use base::rank::*;
         ii
     }
 
+    fn scan_closing_bracket(chrs :&[char], i :usize) -> usize {
+        // like scan_with_limit but '\\]' does not close the value
+        let mut ii = i;
+        while let Some(&ch) = chrs.get(ii) {
+            if ch == ']' {
+                return ii
+            } else if ch == '\\' {
+                ii += 1;
+            }
+            ii += 1;
+        }
+        chrs.len()
+    }
+
     fn sub_string(chrs :&Vec<char>, start :usize, end :usize) -> String {
         let mut s = String::with_capacity(end-start);
         let mut i = start;
@@ -209,7 +303,12 @@ use base::rank::*;
                         s.push(ch);
                         i = i + 1;
                     } else {
-                        i = skip_cr_lf_sp(chrs, i+1);
+                        // escaped char, or a soft line break which is removed
+                        match chrs.get(i+1) {
+                            Some(&'\n') | Some(&'\r') => i = skip_line_break(chrs, i+1),
+                            Some(&next) if i+1 < end => { s.push(next); i += 2 },
+                            _ => i += 1
+                        }
                     }
                 },
                 None => return s
@@ -218,6 +317,13 @@ use base::rank::*;
         s
     }
 
+    fn skip_line_break(chrs :&[char], i :usize) -> usize {
+        match (chrs.get(i), chrs.get(i+1)) {
+            (Some(&'\r'), Some(&'\n')) | (Some(&'\n'), Some(&'\r')) => i + 2,
+            _ => i + 1
+        }
+    }
+
     fn up_string(chrs :&Vec<char>, start :usize, end :usize) -> String {
         sub_string(chrs, start, end).to_uppercase()
     }
@@ -227,7 +333,7 @@ use base::rank::*;
         let mut ii = i;
         loop {
             if let Some(&ch) = chrs.get(ii) {
-                if ch == '\n' || ch == '\r' || ch == ' ' {
+                if ch == '\n' || ch == '\r' || ch == ' ' || ch == '\t' {
                     ii = ii + 1
                 } else {
                     return ii
@@ -238,8 +344,123 @@ use base::rank::*;
         }
     }
 
-    pub fn write() -> String {
-        String::new()
+    /// Serialises all the games into one SGF collection, see `write`.
+    pub fn write_collection(gts :&[GameTree]) -> String {
+        gts.iter().map(write).collect()
+    }
+
+    /// Serialises the GameTree into a FF[4] SGF, variants are written as sub-trees.
+    pub fn write(gt :&GameTree) -> String {
+        let board_size = if gt.board_size() == 0 { 19 } else { gt.board_size() };
+        let mut s = String::new();
+        s.push_str("(;GM[1]FF[4]");
+        write_property(&mut s, "SZ", &format!("{}", board_size));
+        write_property(&mut s, "KM", &format!("{}", gt.komi()));
+        if gt.handicap() > 0 {
+            write_property(&mut s, "HA", &format!("{}", gt.handicap()));
+        }
+        if let Some(name) = gt.rules().and_then(|r| r.name()) {
+            write_property(&mut s, "RU", name);
+        }
+        if !gt.white_name().is_empty() {
+            write_property(&mut s, "PW", gt.white_name());
+        }
+        if *gt.white_rank() != Rank::Unknown {
+            write_property(&mut s, "WR", &format!("{}", gt.white_rank()));
+        }
+        if !gt.black_name().is_empty() {
+            write_property(&mut s, "PB", gt.black_name());
+        }
+        if *gt.black_rank() != Rank::Unknown {
+            write_property(&mut s, "BR", &format!("{}", gt.black_rank()));
+        }
+        if *gt.result() != GameResult::Unknown {
+            write_property(&mut s, "RE", &format!("{}", gt.result()));
+        }
+        write_node_properties(&mut s, gt.root(), board_size);
+        s.push('\n');
+        if gt.root().has_variants() {
+            write_variants(&mut s, gt.root(), gt.moves(), board_size);
+        } else {
+            write_sequence(&mut s, gt.moves(), board_size);
+        }
+        s.push_str(")\n");
+        s
+    }
+
+    fn write_sequence(s :&mut String, nodes :&[GameNode], board_size :usize) {
+        for (i, gn) in nodes.iter().enumerate() {
+            s.push(';');
+            write_node_properties(s, gn, board_size);
+            if gn.has_variants() {
+                write_variants(s, gn, &nodes[i+1..], board_size);
+                return
+            }
+        }
+    }
+
+    fn write_variants(s :&mut String, gn :&GameNode, rest :&[GameNode], board_size :usize) {
+        // the line being written continues as the first sub-tree
+        if !rest.is_empty() {
+            s.push_str("\n(");
+            write_sequence(s, rest, board_size);
+            s.push(')');
+        }
+        for line in gn.variant_lines() {
+            s.push_str("\n(");
+            write_sequence(s, line, board_size);
+            s.push(')');
+        }
+    }
+
+    fn write_node_properties(s :&mut String, gn :&GameNode, board_size :usize) {
+        for &(cmd, color) in &[("AB", Color::Black), ("AW", Color::White), ("AE", Color::Empty)] {
+            let coords : Vec<String> = gn.setup().iter()
+                .filter(|m| m.color() == color)
+                .map(|m| m.coord().to_sgf(board_size as u8))
+                .collect();
+            if !coords.is_empty() {
+                s.push_str(cmd);
+                for coord in coords {
+                    write_value(s, &coord);
+                }
+            }
+        }
+        if let Some(player) = gn.player() {
+            write_property(s, "PL", if player == Color::Black { "B" } else { "W" });
+        }
+        if let Some(m) = gn.themove() {
+            let cmd = if m.color() == Color::Black { "B" } else { "W" };
+            match m {
+                Move::Pass(_)      => write_property(s, cmd, ""),
+                Move::Stone(c, _)  => write_property(s, cmd, &c.to_sgf(board_size as u8))
+            }
+        }
+        if !gn.comment().is_empty() {
+            write_property(s, "C", gn.comment());
+        }
+        for (cmd, values) in gn.properties() {
+            s.push_str(cmd);
+            for value in values {
+                write_value(s, value);
+            }
+        }
+    }
+
+    fn write_property(s :&mut String, cmd :&str, value :&str) {
+        s.push_str(cmd);
+        write_value(s, value);
+    }
+
+    fn write_value(s :&mut String, value :&str) {
+        s.push('[');
+        for ch in value.chars() {
+            if ch == ']' || ch == '\\' {
+                s.push('\\');
+            }
+            s.push(ch);
+        }
+        s.push(']');
     }
 
 
@@ -253,10 +474,6 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use base::moves::*;
-    use base::color::*;
-    use base::rank::*;
-    use base::game_result::*;
 
     #[test]
     fn no_moves_just_headers() {
@@ -270,6 +487,33 @@ AP[CGoban:3]\nTM[0]\nOT[3x10 byo-yomi] HA[4])".to_string()).unwrap();
         assert_eq!(Rank::Dan(6, true), *gt.black_rank());
         assert_eq!(6.5, gt.komi());
         assert_eq!(4, gt.handicap());
+        assert_eq!(Some(Rules::japanese()), gt.rules());
+        assert!(gt.root().property("RU").is_none());
+    }
+
+    #[test]
+    fn it_keeps_unknown_rules_as_properties() {
+        let gt = parse("(;GM[1]FF[4]SZ[19]RU[GOE])".to_string()).unwrap();
+        assert_eq!(None, gt.rules());
+        assert_eq!(Some(&vec!("GOE".to_string())), gt.root().property("RU"));
+        assert!(write(&gt).contains("RU[GOE]"));
+    }
+
+    #[test]
+    fn it_reads_points_from_properties() {
+        let gt = parse("(;SZ[9]TB[aa:bb][ee]TW[zz])".to_string()).unwrap();
+        let tb = property_points(gt.root(), "TB", 9).unwrap();
+        assert_eq!(5, tb.len());
+        assert!(tb.contains(&Coord::from_str("E5").unwrap()));
+        assert!(property_points(gt.root(), "TW", 9).is_err());
+        assert!(property_points(gt.root(), "AB", 9).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_writes_known_rules() {
+        let gt = parse("(;GM[1]FF[4]SZ[9]KM[7]RU[nz];B[ee])".to_string()).unwrap();
+        assert_eq!("(;GM[1]FF[4]SZ[9]KM[7]RU[NZ]\n;B[ee])\n", write(&gt));
+        assert_eq!(Some(Rules::new_zealand()), parse(write(&gt)).unwrap().rules());
     }
 
     #[test]
@@ -312,10 +556,10 @@ AP[CGoban:3]\nTM[0]\nOT[3x10 byo-yomi] HA[4])".to_string()).unwrap();
         assert_eq!(Rank::Dan(7, true), *gt.white_rank());
         assert_eq!(GameResult::Resign(Color::Black), *gt.result());
         assert_eq!(240, gt.moves().len());
-        assert_eq!(Move::from_str("Black N11").unwrap(), gt.moves()[173].themove());
-        assert_eq!(Move::from_str("White H9").unwrap(), gt.moves()[44].themove());
-        assert_eq!(Move::from_str("White R4").unwrap(), gt.moves()[0].themove());
-        assert_eq!(Move::from_str("Black D7").unwrap(), gt.moves()[239].themove());
+        assert_eq!(Some(Move::from_str("Black N11").unwrap()), gt.moves()[173].themove());
+        assert_eq!(Some(Move::from_str("White H9").unwrap()), gt.moves()[44].themove());
+        assert_eq!(Some(Move::from_str("White R4").unwrap()), gt.moves()[0].themove());
+        assert_eq!(Some(Move::from_str("Black D7").unwrap()), gt.moves()[239].themove());
     }
 
     #[test]
@@ -328,6 +572,328 @@ AP[CGoban:3]\nTM[0]\nOT[3x10 byo-yomi] HA[4])".to_string()).unwrap();
         assert_eq!(GameResult::Score(Color::White, 2.5), *gt.result());
     }
 
+    fn assert_same_game_tree(gt :&GameTree, gt2 :&GameTree) {
+        assert_eq!(gt.board_size(), gt2.board_size());
+        assert_eq!(gt.komi(), gt2.komi());
+        assert_eq!(gt.handicap(), gt2.handicap());
+        assert_eq!(gt.black_name(), gt2.black_name());
+        assert_eq!(gt.white_name(), gt2.white_name());
+        assert_eq!(gt.black_rank(), gt2.black_rank());
+        assert_eq!(gt.white_rank(), gt2.white_rank());
+        assert_eq!(gt.result(), gt2.result());
+        assert_same_nodes(&[gt.root().clone()], &[gt2.root().clone()]);
+        assert_same_nodes(gt.moves(), gt2.moves());
+    }
+
+    fn assert_same_nodes(nodes :&[GameNode], nodes2 :&[GameNode]) {
+        assert_eq!(nodes.len(), nodes2.len());
+        for (gn, gn2) in nodes.iter().zip(nodes2.iter()) {
+            assert_eq!(gn.themove(), gn2.themove());
+            assert_eq!(gn.comment(), gn2.comment());
+            assert_eq!(gn.variant_lines().len(), gn2.variant_lines().len());
+            for (line, line2) in gn.variant_lines().iter().zip(gn2.variant_lines().iter()) {
+                assert_same_nodes(line, line2);
+            }
+        }
+    }
+
+    fn amove(s :&str) -> Move {
+        Move::from_str(s).unwrap()
+    }
+
+    static COMMENTED_GAME : &str = "(;GM[1]FF[4]SZ[19]PB[Honinbo Shusaku]PW[Gennan Inseki]KM[0]RE[B+2]
+        C[The ear-reddening game]
+        ;B[qd];W[dc];B[pq];W[oc];B[cp];W[cf];B[ep];W[qo]
+        ;B[pe]C[Black plays the small knight]
+        (;W[np];B[po];W[lq]C[as played in the game])
+        (;W[op]C[a variation to consider]
+            (;B[oo];W[no])
+            (;B[nq];W[oq]C[nested variation]))
+        (;W[qq]C[the simplest answer]))";
+
+    static PROBLEM : &str = "(;GM[1]FF[4]SZ[19]AB[ab][bb][cb][db]AW[ac][bc][cc][dc][ec]C[Black to live]
+        (;B[eb]C[Correct];W[fb];B[ea]C[RIGHT])
+        (;B[ea];W[eb]C[Wrong, white kills])
+        (;B[fb]
+            (;W[eb])
+            (;W[ea]C[also wrong])))";
+
+    #[test]
+    fn it_parses_variants_in_a_commented_game() {
+        let gt = parse(COMMENTED_GAME.to_string()).unwrap();
+        assert_eq!("The ear-reddening game", gt.root().comment());
+        assert!(!gt.root().has_variants());
+
+        // main line is the first variation
+        assert_eq!(12, gt.moves().len());
+        assert_eq!(Some(amove("Black R16")), gt.moves()[0].themove());
+        assert_eq!(Some(amove("White M3")), gt.moves()[11].themove());
+        assert_eq!("as played in the game", gt.moves()[11].comment());
+
+        // variants hang from the branching node
+        let branch = &gt.moves()[8];
+        assert_eq!(Some(amove("Black Q15")), branch.themove());
+        assert_eq!(2, branch.variant_lines().len());
+        let first = &branch.variant_lines()[0];
+        assert_eq!(3, first.len());
+        assert_eq!(Some(amove("White P4")), first[0].themove());
+        assert_eq!("a variation to consider", first[0].comment());
+        assert_eq!(Some(amove("White O5")), first[2].themove());
+        assert_eq!(1, first[0].variant_lines().len());
+        assert_eq!(Some(amove("Black O3")), first[0].variants()[0].themove());
+        assert_eq!("nested variation", first[0].variants()[1].comment());
+        let second = &branch.variant_lines()[1];
+        assert_eq!(1, second.len());
+        assert_eq!(Some(amove("White R3")), second[0].themove());
+
+        // no other node has variants
+        assert_eq!(1, gt.moves().iter().filter(|gn| gn.has_variants()).count());
+    }
+
+    #[test]
+    fn it_parses_variants_from_the_root_node() {
+        let gt = parse(PROBLEM.to_string()).unwrap();
+        assert_eq!("Black to live", gt.root().comment());
+        assert_eq!(3, gt.moves().len());
+        assert_eq!(Some(amove("Black E18")), gt.moves()[0].themove());
+        assert_eq!("RIGHT", gt.moves()[2].comment());
+
+        assert_eq!(2, gt.root().variant_lines().len());
+        assert_eq!(Some(amove("Black E19")), gt.root().variant_lines()[0][0].themove());
+        assert_eq!("Wrong, white kills", gt.root().variant_lines()[0][1].comment());
+        let third = &gt.root().variant_lines()[1];
+        assert_eq!(2, third.len());
+        assert_eq!(Some(amove("White E18")), third[1].themove());
+        assert_eq!(Some(amove("White E19")), third[0].variants()[0].themove());
+    }
+
+    #[test]
+    fn it_roundtrips_variants() {
+        for sgf in &[COMMENTED_GAME, PROBLEM] {
+            let gt = parse(sgf.to_string()).unwrap();
+            let gt2 = parse(write(&gt)).unwrap();
+            assert_same_game_tree(&gt, &gt2);
+        }
+    }
+
+    #[test]
+    fn it_keeps_nodes_without_moves() {
+        let gt = parse("(;SZ[9];B[ee];C[just a comment];W[cc])".to_string()).unwrap();
+        assert_eq!(3, gt.moves().len());
+        assert_eq!(None, gt.moves()[1].themove());
+        assert_eq!("just a comment", gt.moves()[1].comment());
+        assert_same_game_tree(&gt, &parse(write(&gt)).unwrap());
+    }
+
+    static KGS_GAME : &str = "(;GM[1]FF[4]CA[UTF-8]AP[CGoban:3]ST[2]RU[Japanese]SZ[19]KM[6.50]TM[300]
+        OT[5x30 byo-yomi]GN[Friday game]EV[Club \\[league\\]]RO[3]PW[white]PB[black]WR[2k]BR[1k]
+        DT[2016-02-12]PC[The KGS Go Server at http://www.gokgs.com/]RE[B+3.50]
+        ;B[pd]BL[299.5];W[dp]WL[298.1]C[hello]TR[pd][dp];B[pp]LB[dd:A][dc:B]MN[3];W[dd]XYZ[];B[])";
+
+    #[test]
+    fn it_keeps_unknown_properties() {
+        let gt = parse(KGS_GAME.to_string()).unwrap();
+        // known ones are decoded
+        assert_eq!(19, gt.board_size());
+        assert_eq!(6.5, gt.komi());
+        assert_eq!("white", gt.white_name());
+        assert_eq!(Rank::Kyu(1, true), *gt.black_rank());
+        assert_eq!(GameResult::Score(Color::Black, 3.5), *gt.result());
+        assert!(gt.root().property("KM").is_none());
+        assert!(gt.root().property("PW").is_none());
+        assert_eq!(Some(Rules::japanese()), gt.rules());
+        assert!(gt.root().property("RU").is_none());
+
+        // the others are kept
+        let root = gt.root();
+        assert_eq!(&vec!("Friday game".to_string()), root.property("GN").unwrap());
+        assert_eq!(&vec!("Club [league]".to_string()), root.property("EV").unwrap());
+        assert_eq!(&vec!("2016-02-12".to_string()), root.property("DT").unwrap());
+        assert_eq!(&vec!("5x30 byo-yomi".to_string()), root.property("OT").unwrap());
+
+        assert_eq!(&vec!("299.5".to_string()), gt.moves()[0].property("BL").unwrap());
+        assert_eq!("hello", gt.moves()[1].comment());
+        assert_eq!(&vec!("pd".to_string(), "dp".to_string()), gt.moves()[1].property("TR").unwrap());
+        assert_eq!(&vec!("dd:A".to_string(), "dc:B".to_string()), gt.moves()[2].property("LB").unwrap());
+        assert_eq!(&vec!("".to_string()), gt.moves()[3].property("XYZ").unwrap());
+        assert!(gt.moves()[4].properties().is_empty());
+    }
+
+    #[test]
+    fn it_roundtrips_unknown_properties() {
+        let gt = parse(KGS_GAME.to_string()).unwrap();
+        let gt2 = parse(write(&gt)).unwrap();
+        assert_same_game_tree(&gt, &gt2);
+        assert_eq!(gt.root().properties(), gt2.root().properties());
+        for (gn, gn2) in gt.moves().iter().zip(gt2.moves().iter()) {
+            assert_eq!(gn.properties(), gn2.properties());
+        }
+    }
+
+    #[test]
+    fn it_fails_on_invalid_property_identifiers() {
+        assert!(parse("(;SZ[9];B[ee]1X[aa])".to_string()).is_err());
+        assert!(parse("(;SZ[9];[aa])".to_string()).is_err());
+    }
+
+    #[test]
+    fn it_fails_on_unbalanced_variants() {
+        assert!(parse("(;SZ[9];B[ee](;W[cc])".to_string()).is_err());
+        assert!(parse("(;SZ[9];B[ee](;W[cc]))(".to_string()).is_err());
+        assert!(parse("(;SZ[9];B[ee]())".to_string()).is_err());
+    }
+
+    #[test]
+    fn it_writes_headers_and_moves() {
+        let mut gt = GameTree::new();
+        gt.set_board_size(9);
+        gt.set_komi(5.5);
+        gt.set_white_name("white".to_string());
+        gt.set_black_name("black".to_string());
+        gt.set_black_rank(Rank::Dan(2, true));
+        gt.set_result(GameResult::Score(Color::Black, 3.5));
+        gt.push(GameNode::new_simple(Move::from_str("Black E5").unwrap()));
+        gt.push(GameNode::new(Move::from_str("White C3").unwrap(), "a comment"));
+        gt.push(GameNode::new_simple(Move::from_str("Black Pass").unwrap()));
+
+        assert_eq!("(;GM[1]FF[4]SZ[9]KM[5.5]PW[white]PB[black]BR[2d]RE[B+3.5]\n;B[ee];W[cg]C[a comment];B[])\n", write(&gt));
+    }
+
+    #[test]
+    fn it_roundtrips_a_game() {
+        let gt = parse("(;GM[1]FF[4]SZ[19]PW[somerville]WR[7d]PB[yz221]BR[5d]KM[0.50]RE[B+Resign]HA[2]
+                      ;W[qp];B[dd];W[fq];B[op];W[mp];B[on];W[qn];B[qq];W[rq];B[pq];W[ro];B[ip]
+                      ;W[mn];B[ol];W[fo];B[dn];W[in];B[gp];W[fp];B[];W[ho];B[io];W[];B[gn])"
+                      .to_string()).unwrap();
+        let gt2 = parse(write(&gt)).unwrap();
+        assert_same_game_tree(&gt, &gt2);
+        assert_eq!(24, gt2.moves().len());
+        assert_eq!(Some(Move::Pass(Color::Black)), gt2.moves()[19].themove());
+    }
+
+    #[test]
+    fn it_roundtrips_comments_with_escaped_chars() {
+        let mut gt = GameTree::new();
+        gt.set_board_size(19);
+        gt.push(GameNode::new(Move::from_str("Black Q16").unwrap(), "brackets [like this] and \\ slashes"));
+        gt.push(GameNode::new(Move::from_str("White D4").unwrap(), "multi\nline"));
+        let gt2 = parse(write(&gt)).unwrap();
+        assert_same_game_tree(&gt, &gt2);
+    }
+
+    #[test]
+    fn it_parses_soft_line_breaks_in_comments() {
+        let gt = parse("(;SZ[19];B[pd]C[one \\\ntwo])".to_string()).unwrap();
+        assert_eq!("one two", gt.moves()[0].comment());
+    }
+
+    #[test]
+    fn it_writes_variants_as_sub_trees() {
+        // B-A1 -> W-A2 -> B-A3 -> W-A4
+        //             \-> B-B3 -> W-B4
+        let mut gt = GameTree::new();
+        gt.set_board_size(19);
+        gt.push(GameNode::new_simple(Move::from_str("Black A1").unwrap()));
+        gt.push(GameNode::new_simple(Move::from_str("White A2").unwrap()));
+        gt.push(GameNode::new_simple(Move::from_str("Black A3").unwrap()));
+        gt.push(GameNode::new_simple(Move::from_str("White A4").unwrap()));
+        {
+            let sub_node = &mut gt.moves_as_mut()[1];
+            sub_node.push(GameNode::new_simple(Move::from_str("Black B3").unwrap()));
+            sub_node.push(GameNode::new_simple(Move::from_str("White B4").unwrap()));
+        }
+        assert_eq!("(;GM[1]FF[4]SZ[19]KM[0]\n;B[as];W[ar]\n(;B[aq];W[ap])\n(;B[bq];W[bp]))\n", write(&gt));
+    }
+
+    #[test]
+    fn it_parses_setup_stones() {
+        let gt = parse(PROBLEM.to_string()).unwrap();
+        let setup = gt.root().setup();
+        assert_eq!(9, setup.len());
+        assert_eq!(4, setup.iter().filter(|m| m.color() == Color::Black).count());
+        assert_eq!(5, setup.iter().filter(|m| m.color() == Color::White).count());
+        assert!(setup.contains(&amove("Black A18")));
+        assert!(setup.contains(&amove("White E17")));
+        assert!(gt.root().player().is_none());
+        assert!(gt.root().property("AB").is_none());
+    }
+
+    #[test]
+    fn it_parses_compressed_setup_stones_removals_and_player() {
+        let gt = parse("(;SZ[9]HA[2]AB[cc:dd][gg]PL[W];W[ee];AE[gg:gg][cc]AW[aa]PL[b])".to_string()).unwrap();
+        let root = gt.root();
+        assert_eq!(5, root.setup().len());
+        for coord in &["C7", "D7", "C6", "D6", "G3"] {
+            assert!(root.setup().contains(&Move::Stone(Coord::from_str(coord).unwrap(), Color::Black)));
+        }
+        assert_eq!(Some(Color::White), root.player());
+
+        let gn = &gt.moves()[1];
+        assert_eq!(None, gn.themove());
+        assert_eq!(3, gn.setup().len());
+        assert!(gn.setup().contains(&Move::Stone(Coord::from_str("G3").unwrap(), Color::Empty)));
+        assert!(gn.setup().contains(&Move::Stone(Coord::from_str("C7").unwrap(), Color::Empty)));
+        assert!(gn.setup().contains(&Move::Stone(Coord::from_str("A9").unwrap(), Color::White)));
+        assert_eq!(Some(Color::Black), gn.player());
+    }
+
+    #[test]
+    fn it_roundtrips_setup_stones() {
+        let gt = parse("(;SZ[9]HA[2]AB[cc:dd][gg]PL[W];W[ee];AE[gg][cc]AW[aa]PL[B])".to_string()).unwrap();
+        let gt2 = parse(write(&gt)).unwrap();
+        assert_same_game_tree(&gt, &gt2);
+        // the order of setup stones inside a node does not matter
+        for &(gn, gn2) in &[(gt.root(), gt2.root()), (&gt.moves()[1], &gt2.moves()[1])] {
+            assert_eq!(gn.setup().len(), gn2.setup().len());
+            assert!(gn.setup().iter().all(|m| gn2.setup().contains(m)));
+            assert_eq!(gn.player(), gn2.player());
+        }
+    }
+
+    #[test]
+    fn it_fails_on_invalid_setup() {
+        assert!(parse("(;SZ[9]AB[zz])".to_string()).is_err());
+        assert!(parse("(;SZ[9]AB[aa:bb:cc])".to_string()).is_err());
+        assert!(parse("(;SZ[9]PL[X])".to_string()).is_err());
+    }
+
+    #[test]
+    fn it_parses_collections() {
+        let collection = format!("{}\n{}\n\n{}\n", COMMENTED_GAME, PROBLEM, KGS_GAME);
+        assert!(parse(collection.clone()).is_err());
+
+        let gts = parse_collection(collection).unwrap();
+        assert_eq!(3, gts.len());
+        assert_eq!("Honinbo Shusaku", gts[0].black_name());
+        assert_eq!("Black to live", gts[1].root().comment());
+        assert_eq!("white", gts[2].white_name());
+        assert_eq!(6.5, gts[2].komi());
+    }
+
+    #[test]
+    fn it_parses_a_single_game_as_a_collection() {
+        let gts = parse_collection(PROBLEM.to_string()).unwrap();
+        assert_eq!(1, gts.len());
+        assert_eq!(3, gts[0].moves().len());
+    }
+
+    #[test]
+    fn it_roundtrips_collections() {
+        let gts = parse_collection(format!("{}{}{}", COMMENTED_GAME, PROBLEM, KGS_GAME)).unwrap();
+        let gts2 = parse_collection(write_collection(&gts)).unwrap();
+        assert_eq!(gts.len(), gts2.len());
+        for (gt, gt2) in gts.iter().zip(gts2.iter()) {
+            assert_same_game_tree(gt, gt2);
+        }
+    }
+
+    #[test]
+    fn it_fails_on_broken_collections() {
+        assert!(parse_collection("".to_string()).is_err());
+        assert!(parse_collection(format!("{}(;SZ[9];B[ee]", PROBLEM)).is_err());
+        assert!(parse_collection(format!("{} garbage", PROBLEM)).is_err());
+    }
+
     #[test]
     fn it_fails_on_empty_input() {
         assert!(parse("".to_string()).is_err());
@@ -106,8 +106,8 @@ fn calculate_result() {
         Err(e) => panic!("I couldn't parse the SGF file: {:?}",e)
     }
     let mut game = game::Game::new_for_gametree(&gt);
-    for m in gt.moves() {
-        assert!(game.play(m.themove()));
+    for gn in gt.moves() {
+        assert!(game.play_node(gn));
         //println!("{}", game.pretty_print());
     }
 
@@ -150,10 +150,11 @@ fn mred_play() {
         let suggested_move = mred.suggested_move();
         mred.game_as_mut().play( suggested_move );
 
-        println!("MrEd moves: {} (best result is {}) - Suggested move: {}\n{}", &best_move, &best_result, &suggested_move, mred.game().pretty_print());
+        let (hint, estimate) = scorer::influence_scorer_with_board(mred.game());
+        println!("MrEd moves: {} (best result is {}) - Suggested move: {} - Estimated: {}\n{}", best_move, best_result, suggested_move, estimate, mred.game().pretty_print_with_board(&hint));
     }
-    let (board, result) = scorer::optimistic_floodfill_scorer_with_board(mred.game());
-    println!("Final board is: \n{}\nFinal result estimated is {}", mred.game().pretty_print_with_board(&board), result);
+    let (board, result) = scorer::tromp_taylor_scorer_with_board(mred.game());
+    println!("Final board is: \n{}\nFinal result (Tromp-Taylor) is {}", mred.game().pretty_print_with_board(&board), result);
 }
 
 pub fn main() {
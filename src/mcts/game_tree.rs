@@ -82,16 +82,16 @@ impl GameTreeCache {
     }
 
     fn get_as_mut(&mut self, game : &game::Game) -> Option<&mut GameTreeNode> {
-        self.entries.get_mut( &game.board().zobrist() )
+        self.entries.get_mut( &game.situation_hash() )
     }
 
     fn insert(&mut self, generation :u64, game : game::Game) -> Option<GameTreeNode> {
-        self.entries.insert(game.board().zobrist(), GameTreeNode::new(game, generation) )
+        self.entries.insert(game.situation_hash(), GameTreeNode::new(game, generation) )
     }
 
     pub fn get_or_create_as_mut(&mut self, generation : &u64, game : &game::Game) -> &mut GameTreeNode {
         // got a bit crazy with keepting mutability, etc... /TODO: should be more performant
-        if !self.entries.contains_key(&game.board().zobrist()) {
+        if !self.entries.contains_key(&game.situation_hash()) {
             self.insert(generation.clone(), game.clone());
         }
 
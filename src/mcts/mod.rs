@@ -1,3 +1,4 @@
 pub mod game_tree;
 pub mod analytics;
 pub mod mred;
+pub mod ownership;
@@ -1,6 +1,8 @@
 extern crate rand;
 use rand::Rng;
 
+use std::cmp;
+
 use base::*;
 use base::coord::*;
 use base::color::*;
@@ -13,6 +15,9 @@ use mcts::*;
 use mcts::analytics::*;
 use mcts::analytics::brain_keeper::*;
 
+/// how many equally spread points are tried with a playout when placing free handicap.
+static MAX_HANDICAP_TIES : usize = 8;
+
 pub struct MrEd<'r> {
     game      :Game,
     cache     :game_tree::GameTreeCache,
@@ -25,6 +30,7 @@ pub struct MrEd<'r> {
 
 impl<'r> MrEd<'r> {
 
+    /// the playouts, the free handicap ones included, are scored with the given scorer.
     pub fn new(game      :Game,
                keeper    :&'r BrainKeeper,
                scorer    :fn(&Game) -> GameResultRange) -> MrEd<'r> {
@@ -49,18 +55,14 @@ impl<'r> MrEd<'r> {
     pub fn think(&mut self) {
 
         let my_color = self.game.next_turn();
-        let all_coords = Coord::all_possibles(self.game.board().size() as usize);
-
-        for coord in all_coords {
-            let m = Move::Stone(coord, my_color);
-            if Self::is_ok_move(self.game.board(), &m) {
-                let mut game = self.game.clone();
-                if game.play(m) {
-                    let result = Self::super_fast_playout(game);
-                    if result.better_than_for(&self.turn_best_result, my_color) {
-                        self.turn_best_result = result;
-                        self.turn_best_move = m;
-                    }
+
+        for m in self.game.legal_moves() {
+            if m.is_stone() && Self::is_ok_move(self.game.board(), &m) && self.game.play(m) {
+                let result = Self::super_fast_playout(&mut self.game, self.scorer);
+                self.game.undo();
+                if result.better_than_for(&self.turn_best_result, my_color) {
+                    self.turn_best_result = result;
+                    self.turn_best_move = m;
                 }
             }
         }
@@ -78,19 +80,63 @@ impl<'r> MrEd<'r> {
     }
 
 
+    /// Chooses and places its own free handicap stones (GTP's place_free_handicap). The standard
+    /// points go first when the board has them, the rest where they are furthest from the stones
+    /// already placed, and playouts decide between equally spread points.
+    pub fn place_free_handicap(&mut self, handicap :usize) -> Result<Vec<Coord>, SetupError> {
+        let size = self.game.board().size();
+        if handicap < 2 || handicap >= (size as usize).pow(2) {
+            return Err(SetupError::InvalidHandicap(handicap))
+        }
+        let fixed = cmp::min(handicap, Game::max_fixed_handicap(size));
+        let mut coords = Game::fixed_handicap_coords(size, fixed).unwrap_or_default();
+        while coords.len() < handicap {
+            let next = self.most_spread_point(&coords);
+            coords.push(next);
+        }
+        self.game.set_free_handicap(&coords)?;
+        Ok(coords)
+    }
+
+    /// Takes the handicap stones the opponent placed (GTP's set_free_handicap).
+    pub fn set_free_handicap(&mut self, coords :&[Coord]) -> Result<(), SetupError> {
+        self.game.set_free_handicap(coords)
+    }
+
+    fn most_spread_point(&self, placed :&[Coord]) -> Coord {
+        let size = self.game.board().size();
+        let ties : Vec<Coord> = Game::most_spread_points(size, placed).into_iter().take(MAX_HANDICAP_TIES).collect();
+        if ties.len() == 1 {
+            return ties[0]
+        }
+
+        // equally spread, so the one that does best for black in a playout
+        let mut best = ties[0];
+        let mut best_result = GameResultRange::new(GameResult::Draw, 10000);
+        for coord in ties {
+            let mut stones : Vec<Move> = placed.iter().map(|c| Move::Stone(*c, Color::Black)).collect();
+            stones.push(Move::Stone(coord, Color::Black));
+            let mut g = Game::new(size as usize, self.game.komi(), 0);
+            g.set_rules(*self.game.rules());
+            g.setup_stones(&stones).expect("black stones in an almost empty board have liberties");
+            g.set_next_turn(Color::White);
+            let result = Self::super_fast_playout(&mut g, self.scorer);
+            if result.better_than_for(&best_result, Color::Black) {
+                best_result = result;
+                best = coord;
+            }
+        }
+        best
+    }
+
     pub fn new_turn(&mut self) {
         self.turn_best_move = Move::Pass(self.game.next_turn());
         self.turn_best_result = GameResultRange::new(GameResult::Draw, 10000);
     }
 
+    /// anything but filling its own real eyes, false ones need to be filled sooner or later.
     fn is_ok_move(b : &Board, m :&Move) -> bool {
-        if b.get(&m.coord()) != Color::Empty {
-            false
-        } else if b.is_eye(&m.coord(), &m.color()) {
-            false
-        } else {
-            true
-        }
+        b.get(&m.coord()) == Color::Empty && b.eye(&m.coord(), &m.color()) != Some(Eye::Real)
     }
 
     pub fn best_move(&self) -> Move {
@@ -125,27 +171,38 @@ impl<'r> MrEd<'r> {
 
     // ------
 
-    fn super_fast_playout(mut g :Game) -> GameResultRange {
-        let mut rng = rand::thread_rng();
+    // plays until the end and scores, the game is left as it was given
+    fn super_fast_playout(g :&mut Game, scorer :fn(&Game) -> GameResultRange) -> GameResultRange {
+        let start_move_count = g.move_count();
+        Self::play_out(g, &mut rand::thread_rng(), false);
+        let result = scorer(g);
+        while g.move_count() > start_move_count {
+            g.undo();
+        }
+        result
+    }
+
+    /// Plays random moves, never filling real eyes, until the game is finished or it's too long;
+    /// undo the moves to get the game back. Players pass once a few tries find no move, or, if
+    /// pass_only_when_stuck, only when no empty point is left to play, so every stone which
+    /// can't make two eyes ends up taken.
+    pub fn play_out<R :Rng>(g :&mut Game, rng :&mut R, pass_only_when_stuck :bool) {
         let board_size = g.board().size() as usize;
-        while !g.finished() && g.move_count() < 2*board_size*board_size {
+        let max_moves = g.move_count() + 2*board_size*board_size;
+        while !g.finished() && g.move_count() < max_moves {
             let mut count = 0;
             let turn_color = g.next_turn();
-            let mut non_empty : Vec<usize> = vec!();
             // one move
             loop {
                 count = count + 1;
 
                 let coord;
                 if count > board_size/3 {
-                    if non_empty.is_empty() {
-                        non_empty = g.board().data().iter().
-                            enumerate().filter(|&(_,&v)| v == Color::Empty).map(|(n,_)|n).collect();
-                    }
-                    let random = rng.gen::<usize>() % non_empty.len();
-                    coord = g.board().offset_to_coord(random);
+                    let empty = g.board().points(Color::Empty);
+                    let random = rng.gen::<usize>() % cmp::max(1, empty.count());
+                    coord = empty.nth(random).unwrap_or_else(|| Self::random_coord(rng, board_size));
                 } else {
-                    coord = Coord::random(board_size);
+                    coord = Self::random_coord(rng, board_size);
                 }
 
                 let m = Move::Stone(coord, turn_color);
@@ -154,13 +211,117 @@ impl<'r> MrEd<'r> {
                 }
 
                 if count > board_size/2 {
-                    g.play(Move::Pass(turn_color));
+                    if !(pass_only_when_stuck && Self::play_any_empty(g, turn_color, rng)) {
+                        g.play(Move::Pass(turn_color));
+                    }
                     break;
                 }
 
             }
         }
-        scorer::conservative_floodfill_scorer(&g)
     }
 
+    // tries every empty point once from a random one on, true if one was played
+    fn play_any_empty<R :Rng>(g :&mut Game, color :Color, rng :&mut R) -> bool {
+        let empty = g.board().points(Color::Empty);
+        let first = rng.gen::<usize>() % cmp::max(1, empty.count());
+        empty.iter().skip(first).chain(empty.iter().take(first))
+            .map(|c| Move::Stone(c, color))
+            .any(|m| Self::is_ok_move(g.board(), &m) && g.play(m))
+    }
+
+    fn random_coord<R :Rng>(rng :&mut R, board_size :usize) -> Coord {
+        Coord::new_us(rng.gen::<usize>() % board_size, rng.gen::<usize>() % board_size)
+    }
+
+}
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+    use base::scorer;
+
+    #[test]
+    fn it_places_standard_free_handicap_when_there_is_one() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(9, 0.5, 0), &bk, scorer::conservative_floodfill_scorer);
+        let coords = mred.place_free_handicap(4).unwrap();
+        assert_eq!(Game::fixed_handicap_coords(9, 4).unwrap(), coords);
+        assert_eq!(4, mred.game().handicap());
+        assert_eq!(Color::White, mred.game().next_turn());
+    }
+
+    #[test]
+    fn it_spreads_extra_free_handicap_stones() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(13, 0.5, 0), &bk, scorer::conservative_floodfill_scorer);
+        let coords = mred.place_free_handicap(13).unwrap();
+        assert_eq!(13, coords.len());
+        assert_eq!(Game::fixed_handicap_coords(13, 9).unwrap()[..], coords[..9]);
+        for (i, c) in coords.iter().enumerate() {
+            assert_eq!(Color::Black, mred.game().board().get(c));
+            assert!(!coords[..i].contains(c));
+            // not in the first two lines
+            assert!(Game::edge_distance(c, 13) >= 2, "{} is too close to the edge", c);
+        }
+        assert_eq!(13, mred.game().handicap());
+    }
+
+    #[test]
+    fn it_places_free_handicap_in_boards_without_standard_points() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(5, 0.5, 0), &bk, scorer::conservative_floodfill_scorer);
+        let coords = mred.place_free_handicap(2).unwrap();
+        assert_eq!(Coord::from_str("C3").unwrap(), coords[0]);
+        // one of the points of the ring around, all as spread as it gets in a 5x5
+        assert!(Game::most_spread_points(5, &coords[..1]).contains(&coords[1]), "{} is not spread", coords[1]);
+        assert_eq!(1, Game::distance(&coords[0], &coords[1]));
+        assert_eq!(1, Game::edge_distance(&coords[1], 5));
+    }
+
+    #[test]
+    fn it_fills_false_eyes_but_not_real_ones() {
+        let mut board = Board::new(9);
+        for m in &["black a2", "black b1", "black c2", "black b3", "white a3", "white c3"] {
+            board.set_move(Move::from_str(m).unwrap());
+        }
+        // a1 is real, b2 is false as white has two of its diagonals
+        assert!(!MrEd::is_ok_move(&board, &Move::from_str("black a1").unwrap()));
+        assert!(MrEd::is_ok_move(&board, &Move::from_str("black b2").unwrap()));
+        assert!(MrEd::is_ok_move(&board, &Move::from_str("white a1").unwrap()));
+    }
+
+    #[test]
+    fn it_plays_out_with_the_tromp_taylor_scorer() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(5, 0.5, 0), &bk, scorer::tromp_taylor_scorer);
+        mred.think();
+        assert_eq!(0, mred.best_result().range);
+        assert!(mred.best_move().is_stone());
+    }
+
+    #[test]
+    fn it_plays_out_with_the_influence_scorer() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(5, 0.5, 0), &bk, scorer::influence_scorer);
+        mred.think();
+        assert!(mred.best_move().is_stone());
+    }
+
+    #[test]
+    fn it_accepts_the_opponent_free_handicap() {
+        let bk = DefaultBrainKeeper::new(1, 1, 1);
+        let mut mred = MrEd::new(Game::new(9, 0.5, 0), &bk, scorer::conservative_floodfill_scorer);
+        let coords = vec!(Coord::from_str("C3").unwrap(), Coord::from_str("F6").unwrap());
+        assert_eq!(Ok(()), mred.set_free_handicap(&coords));
+        assert_eq!(2, mred.game().handicap());
+        assert_eq!(Err(SetupError::BoardNotEmpty), mred.set_free_handicap(&coords));
+        assert_eq!(Err(SetupError::InvalidHandicap(81)), mred.place_free_handicap(81));
+    }
 }
@@ -0,0 +1,209 @@
+extern crate rand;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use base::*;
+use base::board::*;
+use base::coord::*;
+use base::color::*;
+use base::moves::*;
+use base::game::*;
+use base::game_result::*;
+
+use mcts::mred::*;
+
+/// how many playouts the ownership scorer runs.
+static OWNERSHIP_PLAYOUTS : usize = 64;
+
+/// how sure the playouts must be to give a point to a color, or to take a stone.
+static OWNERSHIP_THRESHOLD : f32 = 0.6;
+
+/// the ownership scorer plays out with a fixed seed, so a position always scores the same.
+static OWNERSHIP_SEED : [u32; 4] = [0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb];
+
+/// How often each point ends up black or white when a position is played out at random, like
+/// the ownership maps other engines show.
+pub struct Ownership {
+    size     :usize,
+    playouts :usize,
+    black    :Vec<u32>,
+    white    :Vec<u32>
+}
+
+impl Ownership {
+
+    /// plays the game out the given times from its position, each playout scored with
+    /// Tromp-Taylor as they end with the board filled but for the eyes.
+    pub fn new(game :&Game, playouts :usize) -> Ownership {
+        Ownership::with_rng(game, playouts, &mut rand::thread_rng())
+    }
+
+    /// as new, the playouts drawing their moves from the given generator.
+    pub fn with_rng<R :Rng>(game :&Game, playouts :usize, rng :&mut R) -> Ownership {
+        let size = game.board().size() as usize;
+        let mut ownership = Ownership {
+            size,
+            playouts,
+            black : vec![0; size * size],
+            white : vec![0; size * size]
+        };
+        let mut g = game.clone();
+        // the passes ending a finished game are taken back so it can be played out too
+        while g.finished() && g.undo().is_some() {}
+        let start = g.move_count();
+        for _ in 0..playouts {
+            MrEd::play_out(&mut g, rng, true);
+            let (marked, _) = scorer::tromp_taylor_scorer_with_board(&g);
+            for c in &(marked.points(Color::Black) | marked.points(Color::BlackTerritory)) {
+                let i = ownership.index(&c);
+                ownership.black[i] += 1;
+            }
+            for c in &(marked.points(Color::White) | marked.points(Color::WhiteTerritory)) {
+                let i = ownership.index(&c);
+                ownership.white[i] += 1;
+            }
+            while g.move_count() > start {
+                g.undo();
+            }
+        }
+        ownership
+    }
+
+    pub fn playouts(&self) -> usize { self.playouts }
+
+    /// the probability of the point ending up black.
+    pub fn black(&self, coord :&Coord) -> f32 {
+        self.black[self.index(coord)] as f32 / self.playouts.max(1) as f32
+    }
+
+    /// the probability of the point ending up white.
+    pub fn white(&self, coord :&Coord) -> f32 {
+        self.white[self.index(coord)] as f32 / self.playouts.max(1) as f32
+    }
+
+    /// from 1.0 if the point is surely black to -1.0 if it's surely white.
+    pub fn get(&self, coord :&Coord) -> f32 {
+        self.black(coord) - self.white(coord)
+    }
+
+    /// the color owning the point at least by threshold, Empty if none does.
+    pub fn owner(&self, coord :&Coord, threshold :f32) -> Color {
+        let own = self.get(coord);
+        if own >= threshold {
+            Color::Black
+        } else if own <= -threshold {
+            Color::White
+        } else {
+            Color::Empty
+        }
+    }
+
+    fn index(&self, coord :&Coord) -> usize {
+        coord.row as usize * self.size + coord.col as usize
+    }
+
+    /// the ownership of every point, row by row from the first one.
+    pub fn grid(&self) -> Vec<Vec<f32>> {
+        (0..self.size)
+            .map(|row| (0..self.size).map(|col| self.get(&Coord::new_us(row, col))).collect())
+            .collect()
+    }
+}
+
+/// stones the playouts mostly lose are dead, and empty points go to the color mostly getting
+/// them or are dame otherwise. It needs many playouts, so it's meant for finished or nearly
+/// finished games rather than as a playout scorer.
+pub fn ownership_scorer_with_board(game :&Game) -> (Board, GameResultRange) {
+    let ownership = Ownership::with_rng(game, OWNERSHIP_PLAYOUTS, &mut XorShiftRng::from_seed(OWNERSHIP_SEED));
+    let board = game.board();
+    let mut marked = board.clone();
+    let mut dead_black = 0;
+    let mut dead_white = 0;
+    for c in Coord::all_possibles(board.size() as usize) {
+        let color = board.get(&c);
+        let mark = match (color, ownership.owner(&c, OWNERSHIP_THRESHOLD)) {
+            (Color::Black, Color::White) => { dead_black += 1; Color::WhiteTerritory },
+            (Color::White, Color::Black) => { dead_white += 1; Color::BlackTerritory },
+            (Color::Empty, Color::Black) => Color::BlackTerritory,
+            (Color::Empty, Color::White) => Color::WhiteTerritory,
+            (Color::Empty, _)            => Color::Dame,
+            _                            => color
+        };
+        if mark != color {
+            marked.set_move(Move::Stone(c, mark));
+        }
+    }
+    let result = scorer::marked_board_result_range(game, &marked, dead_black, dead_white);
+    (marked, result)
+}
+
+pub fn ownership_scorer(game :&Game) -> GameResultRange {
+    ownership_scorer_with_board(game).1
+}
+
+// *********************************************************************************************
+// Tests
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// a 5x5 all black but for eyes at A1 and C1, and the given points.
+    fn black_board(empty :&[&str], white :&[&str]) -> Game {
+        let mut stones = vec!();
+        for c in Coord::all_possibles(5) {
+            stones.push(Move::Stone(c, Color::Black));
+        }
+        for c in ["A1", "C1"].iter().chain(empty.iter()) {
+            stones.push(Move::Stone(Coord::from_str(c).unwrap(), Color::Empty));
+        }
+        for c in white {
+            stones.push(Move::Stone(Coord::from_str(c).unwrap(), Color::White));
+        }
+        let mut g = Game::new(5, 0.5, 0);
+        g.setup_stones(&stones).unwrap();
+        g
+    }
+
+    /// the same playouts every run.
+    fn seeded() -> XorShiftRng {
+        XorShiftRng::from_seed([1, 2, 3, 4])
+    }
+
+    #[test]
+    fn settled_points_are_surely_owned() {
+        let g = black_board(&[], &[]);
+        // alive by Benson's, so no playout can change them
+        assert_eq!(g.board().points(Color::Black), life::unconditionally_alive(g.board(), Color::Black));
+        let ownership = Ownership::new(&g, 10);
+        assert_eq!(10, ownership.playouts());
+        assert_eq!(vec![vec![1.0; 5]; 5], ownership.grid());
+        assert_eq!(1.0, ownership.black(&Coord::from_str("A1").unwrap()));
+        assert_eq!(0.0, ownership.white(&Coord::from_str("A1").unwrap()));
+        assert_eq!(Color::Black, ownership.owner(&Coord::from_str("C1").unwrap(), 1.0));
+    }
+
+    #[test]
+    fn finished_games_are_played_out_from_before_the_passes() {
+        let mut g = black_board(&["D4", "E4", "D5"], &["E5"]);
+        g.play(Move::Pass(Color::Black));
+        g.play(Move::Pass(Color::White));
+        assert!(g.finished());
+        let ownership = Ownership::with_rng(&g, 100, &mut seeded());
+        assert_eq!(Color::Black, ownership.owner(&Coord::from_str("E5").unwrap(), 0.6));
+    }
+
+    #[test]
+    fn stones_which_cant_live_are_taken() {
+        let g = black_board(&["D4", "E4", "D5"], &["E5"]);
+        let ownership = Ownership::with_rng(&g, 100, &mut seeded());
+        assert_eq!(Color::Black, ownership.owner(&Coord::from_str("E5").unwrap(), 0.6));
+        let (board, result) = ownership_scorer_with_board(&g);
+        assert_eq!(Color::BlackTerritory, board.get(&Coord::from_str("E5").unwrap()));
+        assert_eq!(Color::BlackTerritory, board.get(&Coord::from_str("D4").unwrap()));
+        assert_eq!(GameResultRange::new(GameResult::Score(Color::Black, 24.5), 0), result);
+    }
+}
+